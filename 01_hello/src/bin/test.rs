@@ -0,0 +1,3 @@
+fn main() {
+    std::process::exit(hello::test::run(std::env::args_os().collect()));
+}
@@ -0,0 +1,334 @@
+//! `test EXPRESSION` and `[ EXPRESSION ]`: evaluate a POSIX test
+//! expression and answer only through the exit status.
+
+use std::{
+    error::Error,
+    ffi::{CString, OsString},
+    fmt, fs,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt},
+    },
+    path::Path,
+};
+
+/// Exit status when the expression is true
+pub const EXIT_TRUE: i32 = 0;
+/// Exit status when the expression is false
+pub const EXIT_FALSE: i32 = 1;
+/// Exit status when the expression is malformed
+pub const EXIT_ERROR: i32 = 2;
+
+type MyResult<T> = Result<T, TestError>;
+
+#[derive(Debug, PartialEq)]
+pub enum TestError {
+    MissingBracket,
+    MissingParen,
+    MissingArgument(String),
+    ExtraArgument(String),
+    IntegerExpected(String),
+}
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestError::MissingBracket => write!(f, "missing ']'"),
+            TestError::MissingParen => write!(f, "missing ')'"),
+            TestError::MissingArgument(op) => write!(f, "{}: argument expected", op),
+            TestError::ExtraArgument(arg) => write!(f, "{}: unexpected argument", arg),
+            TestError::IntegerExpected(arg) => {
+                write!(f, "{}: integer expression expected", arg)
+            }
+        }
+    }
+}
+
+impl Error for TestError {}
+
+const UNARY: &[&str] = &[
+    "-b", "-c", "-d", "-e", "-f", "-h", "-L", "-n", "-p", "-r", "-s", "-S", "-w", "-x", "-z",
+];
+
+const BINARY: &[&str] = &[
+    "=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge", "-nt", "-ot", "-ef",
+];
+
+fn is_unary(op: &str) -> bool {
+    UNARY.contains(&op)
+}
+
+fn is_binary(op: &str) -> bool {
+    BINARY.contains(&op)
+}
+
+/// `access(2)`, which knows about root, ACLs and read-only mounts
+fn access(path: &str, mode: libc::c_int) -> bool {
+    match CString::new(Path::new(path).as_os_str().as_bytes()) {
+        Ok(path) => unsafe { libc::access(path.as_ptr(), mode) == 0 },
+        Err(_) => false,
+    }
+}
+
+fn unary(op: &str, arg: &str) -> bool {
+    let file_type = || fs::metadata(arg).map(|meta| meta.file_type());
+    match op {
+        "-n" => !arg.is_empty(),
+        "-z" => arg.is_empty(),
+        "-e" => fs::metadata(arg).is_ok(),
+        "-f" => file_type().is_ok_and(|kind| kind.is_file()),
+        "-d" => file_type().is_ok_and(|kind| kind.is_dir()),
+        "-b" => file_type().is_ok_and(|kind| kind.is_block_device()),
+        "-c" => file_type().is_ok_and(|kind| kind.is_char_device()),
+        "-p" => file_type().is_ok_and(|kind| kind.is_fifo()),
+        "-S" => file_type().is_ok_and(|kind| kind.is_socket()),
+        "-L" | "-h" => fs::symlink_metadata(arg).is_ok_and(|meta| meta.file_type().is_symlink()),
+        "-s" => fs::metadata(arg).is_ok_and(|meta| meta.len() > 0),
+        "-r" => access(arg, libc::R_OK),
+        "-w" => access(arg, libc::W_OK),
+        "-x" => access(arg, libc::X_OK),
+        _ => unreachable!("not a unary operator: {}", op),
+    }
+}
+
+fn integer(arg: &str) -> MyResult<i64> {
+    arg.trim()
+        .parse()
+        .map_err(|_| TestError::IntegerExpected(arg.to_string()))
+}
+
+fn binary(left: &str, op: &str, right: &str) -> MyResult<bool> {
+    let modified = |path: &str| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    Ok(match op {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" => integer(left)? == integer(right)?,
+        "-ne" => integer(left)? != integer(right)?,
+        "-lt" => integer(left)? < integer(right)?,
+        "-le" => integer(left)? <= integer(right)?,
+        "-gt" => integer(left)? > integer(right)?,
+        "-ge" => integer(left)? >= integer(right)?,
+        // a missing file is older than any existing one
+        "-nt" => match (modified(left), modified(right)) {
+            (Some(left), Some(right)) => left > right,
+            (left, _) => left.is_some(),
+        },
+        "-ot" => match (modified(left), modified(right)) {
+            (Some(left), Some(right)) => left < right,
+            (_, right) => right.is_some(),
+        },
+        "-ef" => match (fs::metadata(left), fs::metadata(right)) {
+            (Ok(left), Ok(right)) => left.dev() == right.dev() && left.ino() == right.ino(),
+            _ => false,
+        },
+        _ => unreachable!("not a binary operator: {}", op),
+    })
+}
+
+/// A recursive descent parser over the arguments, by precedence
+/// `-o` < `-a` < `!` < primaries and parentheses
+struct Parser<'a> {
+    args: &'a [String],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self, offset: usize) -> Option<&'a str> {
+        self.args.get(self.pos + offset).map(String::as_str)
+    }
+
+    fn take(&mut self) -> Option<&'a str> {
+        let arg = self.peek(0)?;
+        self.pos += 1;
+        Some(arg)
+    }
+
+    fn or(&mut self) -> MyResult<bool> {
+        let mut value = self.and()?;
+        while self.peek(0) == Some("-o") {
+            self.pos += 1;
+            // evaluate both sides so that errors are always reported
+            value |= self.and()?;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> MyResult<bool> {
+        let mut value = self.not()?;
+        while self.peek(0) == Some("-a") {
+            self.pos += 1;
+            value &= self.not()?;
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> MyResult<bool> {
+        if self.peek(0) == Some("!") {
+            self.pos += 1;
+            return Ok(!self.not()?);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> MyResult<bool> {
+        let arg = self
+            .take()
+            .ok_or_else(|| TestError::MissingArgument(self.args[self.pos - 1].clone()))?;
+
+        // `a = b` wins over `( = )` and `-n = x`, as POSIX asks
+        if let (Some(op), Some(right)) = (self.peek(0), self.peek(1)) {
+            if is_binary(op) {
+                self.pos += 2;
+                return binary(arg, op, right);
+            }
+        }
+        if arg == "(" {
+            let value = self.or()?;
+            return match self.take() {
+                Some(")") => Ok(value),
+                Some(extra) => Err(TestError::ExtraArgument(extra.to_string())),
+                None => Err(TestError::MissingParen),
+            };
+        }
+        if is_unary(arg) {
+            let operand = self
+                .take()
+                .ok_or_else(|| TestError::MissingArgument(arg.to_string()))?;
+            return Ok(unary(arg, operand));
+        }
+        Ok(!arg.is_empty())
+    }
+}
+
+/// Evaluate `args` with the POSIX rules for up to four arguments, and the
+/// full grammar beyond that.
+pub fn evaluate(args: &[String]) -> MyResult<bool> {
+    let arg = |i: usize| args[i].as_str();
+    match args.len() {
+        0 => Ok(false),
+        1 => Ok(!arg(0).is_empty()),
+        2 if arg(0) == "!" => Ok(arg(1).is_empty()),
+        2 if is_unary(arg(0)) => Ok(unary(arg(0), arg(1))),
+        2 => Err(TestError::ExtraArgument(arg(1).to_string())),
+        3 if is_binary(arg(1)) => binary(arg(0), arg(1), arg(2)),
+        3 if arg(0) == "!" => evaluate(&args[1..]).map(|value| !value),
+        3 if arg(0) == "(" && arg(2) == ")" => Ok(!arg(1).is_empty()),
+        4 if arg(0) == "!" => evaluate(&args[1..]).map(|value| !value),
+        4 if arg(0) == "(" && arg(3) == ")" => evaluate(&args[1..3]),
+        _ => {
+            let mut parser = Parser { args, pos: 0 };
+            let value = parser.or()?;
+            match parser.peek(0) {
+                Some(extra) => Err(TestError::ExtraArgument(extra.to_string())),
+                None => Ok(value),
+            }
+        }
+    }
+}
+
+/// Run as `test` or, when invoked as `[`, check for and drop the closing
+/// `]`. Takes every argument including the program name and returns the
+/// exit status.
+pub fn run(args: Vec<OsString>) -> i32 {
+    let invoked_as_bracket = args
+        .first()
+        .and_then(|arg0| Path::new(arg0).file_name())
+        .is_some_and(|name| name == "[");
+    let mut args: Vec<String> = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    if invoked_as_bracket && args.pop().as_deref() != Some("]") {
+        eprintln!("[: {}", TestError::MissingBracket);
+        return EXIT_ERROR;
+    }
+
+    match evaluate(&args) {
+        Ok(true) => EXIT_TRUE,
+        Ok(false) => EXIT_FALSE,
+        Err(e) => {
+            eprintln!("test: {}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, TestError};
+
+    fn eval(expr: &str) -> Result<bool, TestError> {
+        let args: Vec<String> = expr.split_whitespace().map(String::from).collect();
+        evaluate(&args)
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(eval(""), Ok(false));
+        assert_eq!(eval("abc"), Ok(true));
+        assert_eq!(evaluate(&["".to_string()]), Ok(false));
+        assert_eq!(eval("-n abc"), Ok(true));
+        assert_eq!(evaluate(&["-z".to_string(), "".to_string()]), Ok(true));
+        assert_eq!(eval("a = a"), Ok(true));
+        assert_eq!(eval("a != a"), Ok(false));
+        assert_eq!(eval("a < b"), Ok(true));
+        assert_eq!(eval("b > a"), Ok(true));
+        // operators as operands
+        assert_eq!(eval("-f"), Ok(true));
+        assert_eq!(eval("! -f"), Ok(false));
+        assert_eq!(eval("= = ="), Ok(true));
+        assert_eq!(eval("( -n )"), Ok(true));
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(eval("10 -eq 10"), Ok(true));
+        assert_eq!(eval("-3 -lt 2"), Ok(true));
+        assert_eq!(eval("3 -ge 4"), Ok(false));
+        assert_eq!(eval("2 -ne 2"), Ok(false));
+        assert_eq!(
+            eval("1 -eq one"),
+            Err(TestError::IntegerExpected("one".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_files() {
+        assert_eq!(eval("-e Cargo.toml"), Ok(true));
+        assert_eq!(eval("-f Cargo.toml"), Ok(true));
+        assert_eq!(eval("-d Cargo.toml"), Ok(false));
+        assert_eq!(eval("-d src"), Ok(true));
+        assert_eq!(eval("-s Cargo.toml"), Ok(true));
+        assert_eq!(eval("-r Cargo.toml"), Ok(true));
+        assert_eq!(eval("-L Cargo.toml"), Ok(false));
+        assert_eq!(eval("-e does/not/exist"), Ok(false));
+        assert_eq!(eval("Cargo.toml -nt does/not/exist"), Ok(true));
+        assert_eq!(eval("does/not/exist -ot Cargo.toml"), Ok(true));
+        assert_eq!(eval("Cargo.toml -ef ./Cargo.toml"), Ok(true));
+    }
+
+    #[test]
+    fn test_logic() {
+        assert_eq!(eval("! a = b"), Ok(true));
+        assert_eq!(eval("a = a -a b = c"), Ok(false));
+        assert_eq!(eval("a = a -o b = c"), Ok(true));
+        // -a binds tighter than -o
+        assert_eq!(eval("a -o b = c -a d = e"), Ok(true));
+        assert_eq!(eval("( a -o b = c ) -a d = e"), Ok(false));
+        assert_eq!(eval("! ( a = a -a -d Cargo.toml )"), Ok(true));
+        assert_eq!(eval("( a = a"), Err(TestError::MissingParen));
+        assert_eq!(eval("a b"), Err(TestError::ExtraArgument("b".to_string())));
+        assert_eq!(
+            eval("a = b c d"),
+            Err(TestError::ExtraArgument("c".to_string()))
+        );
+        assert_eq!(
+            eval("a -a -f"),
+            Err(TestError::MissingArgument("-f".to_string()))
+        );
+    }
+}
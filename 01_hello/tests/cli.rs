@@ -17,3 +17,41 @@ fn false_not_ok() {
     let mut cmd = Command::cargo_bin("false").unwrap();
     cmd.assert().failure();
 }
+
+#[test]
+fn test_status() {
+    let cases: &[(&[&str], i32)] = &[
+        (&["-f", "Cargo.toml"], 0),
+        (&["-d", "Cargo.toml"], 1),
+        (
+            &["!", "-e", "does/not/exist", "-a", "(", "1", "-lt", "2", ")"],
+            0,
+        ),
+        (&["a", "=", "b", "-o", "-z", ""], 0),
+        (&[], 1),
+        (&["1", "-eq", "one"], 2),
+        (&["(", "a"], 2),
+    ];
+    for (args, status) in cases {
+        let mut cmd = Command::cargo_bin("test").unwrap();
+        cmd.args(*args).assert().code(*status).stdout("");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn bracket() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let bracket = dir.join("[");
+    if std::fs::symlink_metadata(&bracket).is_err() {
+        std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_test"), &bracket).unwrap();
+    }
+
+    let mut cmd = Command::new(&bracket);
+    cmd.args(["abc", "!=", "", "]"]).assert().success();
+    let mut cmd = Command::new(&bracket);
+    cmd.args(["abc", "=", ""])
+        .assert()
+        .code(2)
+        .stderr("[: missing ']'\n");
+}
@@ -0,0 +1,173 @@
+//! `--expand`: substitute environment variables like envsubst(1), with the
+//! `${VAR:-default}`, `${VAR:?message}` and `${#VAR}` forms of the shell.
+
+use crate::{EchorError, MyResult};
+
+/// The length of the variable name at the start of `text`
+fn name_len(text: &str) -> usize {
+    match text.chars().next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => text
+            .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+            .unwrap_or(text.len()),
+        _ => 0,
+    }
+}
+
+/// The position of the `}` closing a `${` whose body starts `text`,
+/// skipping over nested `${...}`
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '$' if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                chars.next();
+                depth += 1;
+            }
+            '}' if depth == 0 => return Some(pos),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of one `${...}` with the braces removed
+fn parameter<F>(body: &str, lookup: &F) -> MyResult<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let bad = || EchorError::BadSubstitution(format!("${{{}}}", body));
+
+    if let Some(name) = body.strip_prefix('#') {
+        if name.is_empty() || name_len(name) != name.len() {
+            return Err(bad());
+        }
+        let len = lookup(name).map_or(0, |value| value.chars().count());
+        return Ok(len.to_string());
+    }
+
+    let len = name_len(body);
+    if len == 0 {
+        return Err(bad());
+    }
+    let (name, operator) = body.split_at(len);
+    // `:-` and `:?` treat an empty value like an unset one
+    let value = lookup(name).filter(|value| !value.is_empty());
+    if operator.is_empty() {
+        Ok(value.unwrap_or_default())
+    } else if let Some(default) = operator.strip_prefix(":-") {
+        match value {
+            Some(value) => Ok(value),
+            None => expand_with(default, lookup),
+        }
+    } else if let Some(message) = operator.strip_prefix(":?") {
+        match value {
+            Some(value) => Ok(value),
+            None => {
+                let message = match expand_with(message, lookup)? {
+                    message if message.is_empty() => "parameter null or not set".to_string(),
+                    message => message,
+                };
+                Err(EchorError::UnsetVariable(name.to_string(), message))
+            }
+        }
+    } else {
+        Err(bad())
+    }
+}
+
+/// Expand `text`, looking variables up with `lookup`. A `$` that does not
+/// start a variable is kept as is, and unset variables are empty.
+pub fn expand_with<F>(text: &str, lookup: &F) -> MyResult<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let Some(body) = after.strip_prefix('{') {
+            let end = closing_brace(body)
+                .ok_or_else(|| EchorError::BadSubstitution(format!("${}", after)))?;
+            expanded.push_str(&parameter(&body[..end], lookup)?);
+            rest = &body[end + 1..];
+        } else {
+            let len = name_len(after);
+            if len == 0 {
+                expanded.push('$');
+            } else {
+                expanded.push_str(&lookup(&after[..len]).unwrap_or_default());
+            }
+            rest = &after[len..];
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expand `text` from the process environment
+pub fn expand(text: &str) -> MyResult<String> {
+    expand_with(text, &|name| {
+        std::env::var_os(name).map(|value| value.to_string_lossy().into_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_with;
+    use crate::EchorError;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/me".to_string()),
+            "EMPTY" => Some(String::new()),
+            "WIDE" => Some("héllo".to_string()),
+            _ => None,
+        }
+    }
+
+    fn expand(text: &str) -> Result<String, String> {
+        expand_with(text, &lookup).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_expand_simple() {
+        assert_eq!(expand("plain").unwrap(), "plain");
+        assert_eq!(expand("$HOME/bin").unwrap(), "/home/me/bin");
+        assert_eq!(expand("${HOME}bin").unwrap(), "/home/mebin");
+        assert_eq!(expand("[$UNSET]").unwrap(), "[]");
+        assert_eq!(expand("$ 5$ $1 $").unwrap(), "$ 5$ $1 $");
+    }
+
+    #[test]
+    fn test_expand_operators() {
+        assert_eq!(expand("${UNSET:-none}").unwrap(), "none");
+        assert_eq!(expand("${EMPTY:-none}").unwrap(), "none");
+        assert_eq!(expand("${HOME:-none}").unwrap(), "/home/me");
+        assert_eq!(expand("${UNSET:-$HOME/x}").unwrap(), "/home/me/x");
+        assert_eq!(expand("${UNSET:-${HOME}}!").unwrap(), "/home/me!");
+        assert_eq!(expand("${HOME:?}").unwrap(), "/home/me");
+        assert_eq!(expand("${#WIDE} ${#UNSET}").unwrap(), "5 0");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_eq!(expand("${UNSET:?set it}").unwrap_err(), "UNSET: set it");
+        assert_eq!(
+            expand("${EMPTY:?}").unwrap_err(),
+            "EMPTY: parameter null or not set"
+        );
+        assert!(matches!(
+            expand_with("${HOME", &lookup),
+            Err(EchorError::BadSubstitution(_))
+        ));
+        assert_eq!(expand("${}").unwrap_err(), "${}: bad substitution");
+        assert_eq!(
+            expand("${HOME%x}").unwrap_err(),
+            "${HOME%x}: bad substitution"
+        );
+        assert_eq!(expand("${#1}").unwrap_err(), "${#1}: bad substitution");
+    }
+}
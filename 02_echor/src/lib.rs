@@ -0,0 +1,310 @@
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+};
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
+};
+
+mod expand;
+mod printf;
+
+type MyResult<T> = Result<T, EchorError>;
+
+#[derive(Debug)]
+pub enum EchorError {
+    InvalidConversion(String),
+    InvalidNumber(&'static str, String),
+    UnsetVariable(String, String),
+    BadSubstitution(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for EchorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EchorError::InvalidConversion(spec) => {
+                write!(f, "{}: invalid conversion specification", spec)
+            }
+            EchorError::InvalidNumber(what, val) => write!(f, "invalid {}: '{}'", what, val),
+            EchorError::UnsetVariable(name, message) => write!(f, "{}: {}", name, message),
+            EchorError::BadSubstitution(text) => write!(f, "{}: bad substitution", text),
+            EchorError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for EchorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EchorError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EchorError {
+    fn from(e: io::Error) -> Self {
+        EchorError::Io(e)
+    }
+}
+
+#[derive(Debug)]
+pub struct Config {
+    text: Vec<String>,
+    omit_newline: bool,
+    escapes: bool,
+    format: Option<String>,
+    expand: bool,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const TEXT: Opt = Opt::operand("text");
+    const OMIT_NEWLINE: Opt = Opt::flag("omit_newline").short('n');
+    const ESCAPES: Opt = Opt::flag("escapes").short('e');
+    const NO_ESCAPES: Opt = Opt::flag("no_escapes").short('E');
+    const FORMAT: Opt = Opt::option("format").long("format").conflicts_with(&[
+        "omit_newline",
+        "escapes",
+        "no_escapes",
+    ]);
+    const EXPAND: Opt = Opt::flag("expand").long("expand");
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("echor")
+        .version("0.1.0")
+        .author("Denilson <denilson020898@gmail.com>")
+        .about("Rust echo")
+        .arg(
+            TEXT.arg()
+                .value_name("TEXT")
+                .help("Input text, or the arguments of --format")
+                .required_unless_one(&["format", "print_config"])
+                .min_values(1),
+        )
+        .arg(OMIT_NEWLINE.arg().help("Do no print newline"))
+        .arg(
+            ESCAPES
+                .arg()
+                .help("Interpret backslash escapes")
+                .overrides_with("no_escapes"),
+        )
+        .arg(
+            NO_ESCAPES
+                .arg()
+                .help("Do not interpret backslash escapes (default)")
+                .overrides_with("escapes"),
+        )
+        .arg(
+            FORMAT
+                .arg()
+                .value_name("FMT")
+                .help("Print the TEXT arguments through a printf(1) format"),
+        )
+        .arg(
+            EXPAND
+                .arg()
+                .help("Substitute $VAR, ${VAR}, ${VAR:-default}, ${VAR:?message} and ${#VAR}"),
+        )
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [TEXT, OMIT_NEWLINE, ESCAPES, NO_ESCAPES, FORMAT, EXPAND];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
+
+    Ok(Parsed::Run(Config {
+        text: matches.values_of_lossy("text").unwrap_or_default(),
+        omit_newline: matches.is_present("omit_newline"),
+        escapes: matches.is_present("escapes"),
+        format: matches.value_of("format").map(String::from),
+        expand: matches.is_present("expand"),
+    }))
+}
+
+/// The value of up to `max` leading digits of `text` in `radix`, and how
+/// many bytes they took
+fn leading_digits(text: &str, radix: u32, max: usize) -> Option<(u32, usize)> {
+    let len = text
+        .chars()
+        .take(max)
+        .take_while(|c| c.is_digit(radix))
+        .count();
+    match len {
+        0 => None,
+        _ => u32::from_str_radix(&text[..len], radix)
+            .ok()
+            .map(|value| (value, len)),
+    }
+}
+
+/// How octal escapes are spelled
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Octal {
+    /// `echo -e` and printf's `%b`: `\0` and up to three digits
+    Echo,
+    /// a printf format: one to three digits
+    Printf,
+}
+
+/// Interpret the backslash escapes of `echo -e` in `text`. Returns the
+/// bytes to print and whether `\c` asked to stop all further output.
+fn unescape(text: &str, octal: Octal) -> (Vec<u8>, bool) {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('\\') {
+        bytes.extend_from_slice(&rest.as_bytes()[..pos]);
+        let escape = &rest[pos + 1..];
+        let mut chars = escape.chars();
+        let (byte, used) = match chars.next() {
+            Some('\\') => (b'\\', 1),
+            Some('a') => (0x07, 1),
+            Some('b') => (0x08, 1),
+            Some('c') => return (bytes, true),
+            Some('e') => (0x1b, 1),
+            Some('f') => (0x0c, 1),
+            Some('n') => (b'\n', 1),
+            Some('r') => (b'\r', 1),
+            Some('t') => (b'\t', 1),
+            Some('v') => (0x0b, 1),
+            Some('0') if octal == Octal::Echo => {
+                // \0 followed by up to three octal digits
+                let (value, len) = leading_digits(&escape[1..], 8, 3).unwrap_or((0, 0));
+                (value as u8, 1 + len)
+            }
+            Some('0'..='7') if octal == Octal::Printf => {
+                let (value, len) = leading_digits(escape, 8, 3).unwrap();
+                (value as u8, len)
+            }
+            Some('x') => match leading_digits(&escape[1..], 16, 2) {
+                Some((value, len)) => (value as u8, 1 + len),
+                None => {
+                    bytes.push(b'\\');
+                    rest = escape;
+                    continue;
+                }
+            },
+            Some(kind @ ('u' | 'U')) => {
+                let max = if kind == 'u' { 4 } else { 8 };
+                match leading_digits(&escape[1..], 16, max)
+                    .and_then(|(value, len)| char::from_u32(value).map(|c| (c, len)))
+                {
+                    Some((c, len)) => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                        rest = &escape[1 + len..];
+                        continue;
+                    }
+                    None => {
+                        bytes.push(b'\\');
+                        rest = escape;
+                        continue;
+                    }
+                }
+            }
+            // anything else, or a trailing backslash, is printed as is
+            _ => {
+                bytes.push(b'\\');
+                rest = escape;
+                continue;
+            }
+        };
+        bytes.push(byte);
+        rest = &escape[used..];
+    }
+    bytes.extend_from_slice(rest.as_bytes());
+    (bytes, false)
+}
+
+/// Returns the exit status: 1 if a --format argument was not a number.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    let text = if config.expand {
+        config
+            .text
+            .iter()
+            .map(|text| expand::expand(text))
+            .collect::<MyResult<_>>()?
+    } else {
+        config.text
+    };
+
+    if let Some(format) = &config.format {
+        return printf::printf(format, &text, out);
+    }
+
+    let text = text.join(" ");
+    if config.escapes {
+        let (bytes, stop) = unescape(&text, Octal::Echo);
+        out.write_all(&bytes)?;
+        if stop {
+            return Ok(0);
+        }
+    } else {
+        write!(out, "{}", text)?;
+    }
+    if !config.omit_newline {
+        writeln!(out)?;
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unescape, Octal};
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("plain", Octal::Echo), (b"plain".to_vec(), false));
+        assert_eq!(
+            unescape(r"a\tb\nc\\d\a\b\e\f\r\v", Octal::Echo),
+            (b"a\tb\nc\\d\x07\x08\x1b\x0c\r\x0b".to_vec(), false)
+        );
+        assert_eq!(
+            unescape(r"stop\chere", Octal::Echo),
+            (b"stop".to_vec(), true)
+        );
+    }
+
+    #[test]
+    fn test_unescape_numeric() {
+        assert_eq!(unescape(r"\0101\0", Octal::Echo), (b"A\0".to_vec(), false));
+        assert_eq!(unescape(r"\01234", Octal::Echo), (b"S4".to_vec(), false));
+        assert_eq!(
+            unescape(r"\x41\x4a2\xff", Octal::Echo),
+            (b"AJ2\xff".to_vec(), false)
+        );
+        assert_eq!(
+            unescape(r"\u00e9\U0001F600", Octal::Echo),
+            ("\u{e9}\u{1f600}".as_bytes().to_vec(), false)
+        );
+    }
+
+    #[test]
+    fn test_unescape_literal() {
+        // unknown escapes, escapes without digits and a trailing backslash
+        assert_eq!(
+            unescape(r"\q\x\u\", Octal::Echo),
+            (br"\q\x\u\".to_vec(), false)
+        );
+        // surrogates are not characters
+        assert_eq!(
+            unescape(r"\ud800", Octal::Echo),
+            (br"\ud800".to_vec(), false)
+        );
+    }
+}
@@ -1,27 +1,8 @@
-use clap::{App, Arg};
+use clr_common::output;
 
-// squash me daddy 2 3 3
 fn main() {
-    let matches = App::new("echor")
-        .version("0.1.0")
-        .author("Denilson <denilson020898@gmail.com>")
-        .about("Rust echo")
-        .arg(
-            Arg::with_name("text")
-                .value_name("TEXT")
-                .help("Input text")
-                .required(true)
-                .min_values(1),
-        )
-        .arg(
-            Arg::with_name("omit_newline")
-                .short("n")
-                .help("Do no print newline")
-                .takes_value(false),
-        )
-        .get_matches();
-
-    let text = matches.values_of_lossy("text").unwrap();
-    let omit_newline = matches.is_present("omit_newline");
-    print!("{}{}", text.join(" "), if omit_newline { "" } else { "\n" });
+    std::process::exit(output::execute(
+        |out| echor::get_args().and_then(|parsed| parsed.run(out, echor::run)),
+        1,
+    ));
 }
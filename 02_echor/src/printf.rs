@@ -0,0 +1,578 @@
+//! `echor --format FMT ARGS...`: POSIX printf. The format is reused while
+//! arguments remain; a missing argument reads as "" or 0.
+
+use std::io::Write;
+
+use crate::{unescape, EchorError, MyResult, Octal};
+
+/// The arguments left for the conversions, and the exit status so far
+struct Args<'a> {
+    values: &'a [String],
+    next: usize,
+    status: i32,
+}
+
+impl<'a> Args<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let value = self.values.get(self.next)?;
+        self.next += 1;
+        Some(value)
+    }
+
+    /// Report an argument that is not (entirely) a number; it still prints
+    fn warn(&mut self, arg: &str, msg: &str) {
+        eprintln!("\"{}\": {}", arg, msg);
+        self.status = 1;
+    }
+
+    /// The next argument as an integer: decimal, 0x hex, 0 octal, or the
+    /// code of the character after a leading quote
+    fn integer(&mut self) -> (bool, u64) {
+        let arg = match self.next() {
+            Some(arg) => arg,
+            None => return (false, 0),
+        };
+        let text = arg.trim_start();
+        if text.is_empty() {
+            return (false, 0);
+        }
+        if let Some(quoted) = text.strip_prefix(['\'', '"']) {
+            return (false, quoted.chars().next().map_or(0, u64::from));
+        }
+
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (radix, digits) = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+            .filter(|hex| hex.starts_with(|c: char| c.is_ascii_hexdigit()))
+        {
+            (16, hex)
+        } else if digits.len() > 1 && digits.starts_with('0') {
+            (8, &digits[1..])
+        } else {
+            (10, digits)
+        };
+
+        let len = digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len());
+        if len == 0 && radix == 10 {
+            self.warn(arg, "expected a numeric value");
+            return (false, 0);
+        }
+        let magnitude = match u64::from_str_radix(&digits[..len], radix) {
+            Ok(magnitude) => magnitude,
+            Err(_) if len == 0 => 0,
+            Err(_) => {
+                self.warn(arg, "Numerical result out of range");
+                u64::MAX
+            }
+        };
+        if len < digits.len() {
+            self.warn(arg, "value not completely converted");
+        }
+        (negative, magnitude)
+    }
+
+    /// The next argument as a floating point number
+    fn float(&mut self) -> f64 {
+        let arg = match self.next() {
+            Some(arg) => arg,
+            None => return 0.0,
+        };
+        let text = arg.trim_start();
+        if text.is_empty() {
+            return 0.0;
+        }
+        if let Some(quoted) = text.strip_prefix(['\'', '"']) {
+            return quoted
+                .chars()
+                .next()
+                .map_or(0.0, |c| f64::from(u32::from(c)));
+        }
+
+        // the longest prefix that is a number
+        let end = (1..=text.len())
+            .rev()
+            .filter(|&end| text.is_char_boundary(end))
+            .find(|&end| text[..end].parse::<f64>().is_ok());
+        match end {
+            Some(end) => {
+                if end < text.len() {
+                    self.warn(arg, "value not completely converted");
+                }
+                text[..end].parse().unwrap()
+            }
+            None => {
+                self.warn(arg, "expected a numeric value");
+                0.0
+            }
+        }
+    }
+}
+
+/// One `%` directive: its flags, width, precision and conversion
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+impl Spec {
+    /// `lead` (a sign or radix prefix) and `body` padded to the width, in
+    /// bytes as C does; zeros go between the two for numbers.
+    fn pad(&self, lead: &str, body: &[u8], zero: bool) -> Vec<u8> {
+        let len = lead.len() + body.len();
+        let fill = self.width.saturating_sub(len);
+        let mut padded = Vec::with_capacity(len + fill);
+        if self.left {
+            padded.extend_from_slice(lead.as_bytes());
+            padded.extend_from_slice(body);
+            padded.resize(len + fill, b' ');
+        } else if zero && self.zero {
+            padded.extend_from_slice(lead.as_bytes());
+            padded.resize(lead.len() + fill, b'0');
+            padded.extend_from_slice(body);
+        } else {
+            padded.resize(fill, b' ');
+            padded.extend_from_slice(lead.as_bytes());
+            padded.extend_from_slice(body);
+        }
+        padded
+    }
+
+    fn sign(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.plus {
+            "+"
+        } else if self.space {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    fn integer(&self, negative: bool, magnitude: u64) -> Vec<u8> {
+        let signed = matches!(self.conversion, 'd' | 'i');
+        // unsigned conversions take negative numbers modulo 2^64, like C
+        let magnitude = if negative && !signed {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        };
+        let mut digits = match self.conversion {
+            'o' => format!("{:o}", magnitude),
+            'x' => format!("{:x}", magnitude),
+            'X' => format!("{:X}", magnitude),
+            _ => magnitude.to_string(),
+        };
+        if let Some(precision) = self.precision {
+            if precision == 0 && magnitude == 0 {
+                digits.clear();
+            }
+            if digits.len() < precision {
+                digits.insert_str(0, &"0".repeat(precision - digits.len()));
+            }
+        }
+
+        let lead = match self.conversion {
+            'o' if self.alt && !digits.starts_with('0') => {
+                digits.insert(0, '0');
+                ""
+            }
+            'x' if self.alt && magnitude != 0 => "0x",
+            'X' if self.alt && magnitude != 0 => "0X",
+            _ if signed => self.sign(negative && magnitude != 0),
+            _ => "",
+        };
+        // a precision turns the 0 flag off
+        self.pad(lead, digits.as_bytes(), self.precision.is_none())
+    }
+
+    fn float(&self, value: f64) -> Vec<u8> {
+        let precision = self.precision.unwrap_or(6);
+        let abs = value.abs();
+        let mut body = if abs.is_nan() {
+            "nan".to_string()
+        } else if abs.is_infinite() {
+            "inf".to_string()
+        } else {
+            match self.conversion.to_ascii_lowercase() {
+                'e' => exponential(abs, precision, self.alt),
+                'g' => general(abs, precision, self.alt),
+                _ => {
+                    let mut fixed = format!("{:.*}", precision, abs);
+                    if self.alt && precision == 0 {
+                        fixed.push('.');
+                    }
+                    fixed
+                }
+            }
+        };
+        if self.conversion.is_ascii_uppercase() {
+            body = body.to_uppercase();
+        }
+        let negative = value.is_sign_negative() && !value.is_nan();
+        self.pad(self.sign(negative), body.as_bytes(), value.is_finite())
+    }
+}
+
+/// `%e`: one digit, the fraction and an exponent of at least two digits
+fn exponential(value: f64, precision: usize, alt: bool) -> String {
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!(
+        "{}{}e{}{:02}",
+        mantissa,
+        if alt && precision == 0 { "." } else { "" },
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// `%g`: `%e` for very small or large exponents, `%f` otherwise, with
+/// trailing zeros removed unless `#` was given
+fn general(value: f64, precision: usize, alt: bool) -> String {
+    let precision = precision.max(1);
+    let exponent = if value == 0.0 {
+        0
+    } else {
+        let formatted = format!("{:.*e}", precision - 1, value);
+        formatted.split_once('e').unwrap().1.parse().unwrap()
+    };
+    let mut body = if exponent < -4 || exponent >= precision as i32 {
+        exponential(value, precision - 1, alt)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, value)
+    };
+    if !alt {
+        let (number, exp) = match body.find('e') {
+            Some(pos) => body.split_at(pos),
+            None => (body.as_str(), ""),
+        };
+        if number.contains('.') {
+            body = format!(
+                "{}{}",
+                number.trim_end_matches('0').trim_end_matches('.'),
+                exp
+            );
+        }
+    }
+    body
+}
+
+/// `s` cut to at most `max` bytes without splitting a character
+fn truncate(s: &str, max: usize) -> &str {
+    let mut end = max.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// The widest field, or longest precision, a directive may ask for
+const MAX_WIDTH: u64 = 1 << 20;
+
+/// `value` as a field width or precision, unless it is too big to pad to
+fn bounded(what: &'static str, value: u64, text: &str) -> MyResult<usize> {
+    if value > MAX_WIDTH {
+        Err(EchorError::InvalidNumber(what, text.to_string()))
+    } else {
+        Ok(value as usize)
+    }
+}
+
+/// Parse the directive after a `%` at the start of `text`. Returns it and
+/// the number of bytes it took, or `None` if it is incomplete.
+fn parse_spec(text: &str, args: &mut Args) -> MyResult<Option<(Spec, usize)>> {
+    let mut spec = Spec::default();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(_, c)) = chars.peek() {
+        match c {
+            '-' => spec.left = true,
+            '+' => spec.plus = true,
+            ' ' => spec.space = true,
+            '#' => spec.alt = true,
+            '0' => spec.zero = true,
+            _ => break,
+        }
+        chars.next();
+    }
+
+    let digits = |chars: &mut std::iter::Peekable<std::str::CharIndices>| {
+        let start = chars.peek().map_or(text.len(), |&(i, _)| i);
+        while chars.next_if(|&(_, c)| c.is_ascii_digit()).is_some() {}
+        let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        let digits = &text[start..end];
+        // too many digits for a u64 is too wide as well
+        (digits.parse().unwrap_or(u64::MAX), digits)
+    };
+
+    if chars.peek().map(|&(_, c)| c) == Some('*') {
+        chars.next();
+        let (negative, width) = args.integer();
+        spec.left |= negative;
+        let sign = if negative { "-" } else { "" };
+        spec.width = bounded("field width", width, &format!("{}{}", sign, width))?;
+    } else {
+        let (width, digits) = digits(&mut chars);
+        spec.width = if digits.is_empty() {
+            0
+        } else {
+            bounded("field width", width, digits)?
+        };
+    }
+
+    if chars.peek().map(|&(_, c)| c) == Some('.') {
+        chars.next();
+        spec.precision = if chars.peek().map(|&(_, c)| c) == Some('*') {
+            chars.next();
+            // a negative precision counts as none
+            match args.integer() {
+                (true, _) => None,
+                (false, precision) => {
+                    Some(bounded("precision", precision, &precision.to_string())?)
+                }
+            }
+        } else {
+            // a bare `.` is a precision of 0
+            match digits(&mut chars) {
+                (_, "") => Some(0),
+                (precision, digits) => Some(bounded("precision", precision, digits)?),
+            }
+        };
+    }
+
+    let (pos, conversion) = match chars.next() {
+        Some(next) => next,
+        None => return Ok(None),
+    };
+    spec.conversion = conversion;
+    Ok(Some((spec, pos + conversion.len_utf8())))
+}
+
+/// Write `format` once. Returns false if `\c` stopped all output.
+fn write_once(format: &str, args: &mut Args, out: &mut impl Write) -> MyResult<bool> {
+    let mut rest = format;
+    while !rest.is_empty() {
+        let pos = rest.find('%').unwrap_or(rest.len());
+        let (bytes, stop) = unescape(&rest[..pos], Octal::Printf);
+        out.write_all(&bytes)?;
+        if stop {
+            return Ok(false);
+        }
+        if pos == rest.len() {
+            break;
+        }
+
+        let directive = &rest[pos + 1..];
+        if let Some(after) = directive.strip_prefix('%') {
+            out.write_all(b"%")?;
+            rest = after;
+            continue;
+        }
+        let (spec, len) = parse_spec(directive, args)?
+            .ok_or_else(|| EchorError::InvalidConversion(format!("%{}", directive)))?;
+        let formatted = match spec.conversion {
+            'd' | 'i' | 'u' | 'o' | 'x' | 'X' => {
+                let (negative, magnitude) = args.integer();
+                spec.integer(negative, magnitude)
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => spec.float(args.float()),
+            'c' => {
+                let arg = args.next().unwrap_or("");
+                let c = arg.chars().next().map(String::from).unwrap_or_default();
+                spec.pad("", c.as_bytes(), false)
+            }
+            's' => {
+                let arg = args.next().unwrap_or("");
+                let arg = match spec.precision {
+                    Some(precision) => truncate(arg, precision),
+                    None => arg,
+                };
+                spec.pad("", arg.as_bytes(), false)
+            }
+            'b' => {
+                let (bytes, stop) = unescape(args.next().unwrap_or(""), Octal::Echo);
+                out.write_all(&spec.pad("", &bytes, false))?;
+                if stop {
+                    return Ok(false);
+                }
+                rest = &directive[len..];
+                continue;
+            }
+            _ => {
+                return Err(EchorError::InvalidConversion(format!(
+                    "%{}",
+                    &directive[..len]
+                )))
+            }
+        };
+        out.write_all(&formatted)?;
+        rest = &directive[len..];
+    }
+    Ok(true)
+}
+
+/// Print `args` through `format` like printf(1), repeating the format
+/// while arguments remain. Returns 1 if an argument was not a number.
+pub fn printf(format: &str, values: &[String], out: &mut impl Write) -> MyResult<i32> {
+    let mut args = Args {
+        values,
+        next: 0,
+        status: 0,
+    };
+    loop {
+        let start = args.next;
+        if !write_once(format, &mut args, out)? {
+            break;
+        }
+        // stop once every argument is used, or if the format takes none
+        if args.next == start || args.next >= values.len() {
+            break;
+        }
+    }
+    Ok(args.status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::printf;
+
+    fn run(format: &str, args: &[&str]) -> (String, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut out = vec![];
+        let status = printf(format, &args, &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), status)
+    }
+
+    fn ok(format: &str, args: &[&str]) -> String {
+        let (out, status) = run(format, args);
+        assert_eq!(status, 0);
+        out
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(ok("%s-%s\\n", &["a", "b"]), "a-b\n");
+        assert_eq!(
+            ok("[%5s][%-5s][%.2s]", &["ab", "cd", "efgh"]),
+            "[   ab][cd   ][ef]"
+        );
+        assert_eq!(ok("%c%c", &["xyz", ""]), "x");
+        assert_eq!(ok("100%%\\t\\101\\0", &[]), "100%\tA\0");
+        assert_eq!(ok("%b|%s", &["a\\tb", "a\\tb"]), "a\tb|a\\tb");
+        assert_eq!(ok("%b%s", &["stop\\c", "never"]), "stop");
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(ok("%d %i %d", &["42", "-7", "+3"]), "42 -7 3");
+        assert_eq!(
+            ok("%5d|%-5d|%05d|%+d|% d", &["1", "2", "-3", "4", "5"]),
+            "    1|2    |-0003|+4| 5"
+        );
+        assert_eq!(ok("%.3d|%8.3d|%.0d", &["7", "-7", "0"]), "007|    -007|");
+        assert_eq!(
+            ok("%o %#o %x %#X", &["8", "8", "255", "255"]),
+            "10 010 ff 0XFF"
+        );
+        assert_eq!(ok("%d %d %d", &["0x1f", "010", "'A"]), "31 8 65");
+        assert_eq!(ok("%u", &["-1"]), "18446744073709551615");
+        assert_eq!(ok("%*d|%-*d", &["4", "1", "3", "2"]), "   1|2  ");
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(
+            ok("%f %.2f %8.3f", &["1.5", "2.346", "-3.14159"]),
+            "1.500000 2.35   -3.142"
+        );
+        assert_eq!(
+            ok("%e %.2E", &["12345.678", "0.00012"]),
+            "1.234568e+04 1.20E-04"
+        );
+        assert_eq!(
+            ok("%g %g %g %g", &["100000", "1000000", "0.0001", "0.00001"]),
+            "100000 1e+06 0.0001 1e-05"
+        );
+        assert_eq!(
+            ok("%g %.3g %#g", &["3.14159", "2.5", "1"]),
+            "3.14159 2.5 1.00000"
+        );
+        assert_eq!(
+            ok("%+.1f|%08.2f|%-8.1f|", &["2", "-1.5", "3"]),
+            "+2.0|-0001.50|3.0     |"
+        );
+        assert_eq!(ok("%f %F", &["inf", "-inf"]), "inf -INF");
+    }
+
+    #[test]
+    fn test_reuse() {
+        assert_eq!(
+            ok("%s=%d\\n", &["a", "1", "b", "2", "c"]),
+            "a=1\nb=2\nc=0\n"
+        );
+        assert_eq!(ok("x\\n", &["ignored"]), "x\n");
+        assert_eq!(ok("[%s]", &[]), "[]");
+    }
+
+    #[test]
+    fn test_bad_numbers() {
+        assert_eq!(
+            run("%d|%d|%f", &["abc", "12x", "1.5y"]),
+            ("0|12|1.500000".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_bad_conversion() {
+        let mut out = vec![];
+        let res = printf("a%zb", &[], &mut out);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "%z: invalid conversion specification"
+        );
+        assert_eq!(out, b"a");
+        assert!(printf("trailing %", &[], &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_huge_width() {
+        let fails = |format: &str, args: &[&str]| {
+            let values: Vec<_> = args.iter().map(|s| s.to_string()).collect();
+            printf(format, &values, &mut vec![])
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            fails("%*d", &["99999999999999", "1"]),
+            "invalid field width: '99999999999999'"
+        );
+        assert_eq!(
+            fails("%*d", &["-99999999999999", "1"]),
+            "invalid field width: '-99999999999999'"
+        );
+        assert_eq!(
+            fails("%99999999999999999999999d", &["1"]),
+            "invalid field width: '99999999999999999999999'"
+        );
+        assert_eq!(
+            fails("%.*f", &["99999999999", "1"]),
+            "invalid precision: '99999999999'"
+        );
+        assert_eq!(fails("%.9999999s", &["a"]), "invalid precision: '9999999'");
+        assert_eq!(ok("%*d|%.*s", &["5", "1", "2", "abc"]), "    1|ab");
+    }
+}
@@ -49,3 +49,127 @@ fn hello1_no_newline() -> TestResult {
 fn hello2_no_newline() -> TestResult {
     run(&["Hello", "there", "-n"], "tests/expected/hello2.n.txt")
 }
+
+#[test]
+fn escapes() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-e", r"a\tb\x41", r"\0101é"])
+        .assert()
+        .success()
+        .stdout("a\tbA A\u{e9}\n");
+    Command::cargo_bin("echor")?
+        .args(["-e", r"no\cnewline", "ignored"])
+        .assert()
+        .success()
+        .stdout("no");
+    Ok(())
+}
+
+#[test]
+fn no_escapes() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args([r"a\tb"])
+        .assert()
+        .success()
+        .stdout("a\\tb\n");
+    // the last of -e and -E wins
+    Command::cargo_bin("echor")?
+        .args(["-e", "-E", r"a\tb"])
+        .assert()
+        .success()
+        .stdout("a\\tb\n");
+    Command::cargo_bin("echor")?
+        .args(["-E", "-e", r"a\tb"])
+        .assert()
+        .success()
+        .stdout("a\tb\n");
+    Ok(())
+}
+
+#[test]
+fn format() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--format", r"%-5s|%03d|%.2f\n", "a", "7", "3.14159", "b"])
+        .assert()
+        .success()
+        .stdout("a    |007|3.14\nb    |000|0.00\n");
+    Command::cargo_bin("echor")?
+        .args(["--format", r"%x\n", "--", "-1"])
+        .assert()
+        .success()
+        .stdout("ffffffffffffffff\n");
+    Ok(())
+}
+
+#[test]
+fn format_bad_number() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--format", r"%d\n", "12", "twelve"])
+        .assert()
+        .code(1)
+        .stdout("12\n0\n")
+        .stderr("\"twelve\": expected a numeric value\n");
+    Command::cargo_bin("echor")?
+        .args(["--format", "%y", "1"])
+        .assert()
+        .code(1)
+        .stderr("%y: invalid conversion specification\n");
+    Ok(())
+}
+
+#[test]
+fn expand() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--expand", "$GREETING, ${NAME:-world}!", "(${#GREETING})"])
+        .env("GREETING", "Hello")
+        .env_remove("NAME")
+        .assert()
+        .success()
+        .stdout("Hello, world! (5)\n");
+    // without --expand the text is printed as is
+    Command::cargo_bin("echor")?
+        .args(["$GREETING"])
+        .env("GREETING", "Hello")
+        .assert()
+        .success()
+        .stdout("$GREETING\n");
+    Command::cargo_bin("echor")?
+        .args(["--expand", "--format", "%s=%d\n", "x", "${COUNT}"])
+        .env("COUNT", "42")
+        .assert()
+        .success()
+        .stdout("x=42\n");
+    Ok(())
+}
+
+#[test]
+fn expand_errors() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--expand", "${TOKEN:?must be set}"])
+        .env_remove("TOKEN")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("TOKEN: must be set\n");
+    Command::cargo_bin("echor")?
+        .args(["--expand", "${TOKEN"])
+        .assert()
+        .failure()
+        .stderr("${TOKEN: bad substitution\n");
+    Ok(())
+}
+
+#[test]
+fn generate_only_first() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--", "--generate=bash"])
+        .assert()
+        .success()
+        .stdout("--generate=bash\n");
+    Command::cargo_bin("echor")?
+        .args(["hi", "--generate", "bash"])
+        .assert()
+        .success()
+        .stdout("hi\n");
+    Ok(())
+}
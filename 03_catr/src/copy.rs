@@ -0,0 +1,155 @@
+//! Plain concatenation: bytes go from input to output untouched, through
+//! the kernel when both ends allow it.
+
+use std::io::{self, Read, Write};
+
+use clr_common::{
+    input::{InputError, Opened, Source},
+    output::Sink,
+};
+
+const BUF_SIZE: usize = 128 * 1024;
+
+/// Copy everything left in `reader`, calling `on_read_error` on what
+/// failed to read
+fn copy_buffered<R, F>(reader: &mut R, out: &mut impl Write, on_read_error: F) -> io::Result<()>
+where
+    R: Read,
+    F: Fn(io::Error) -> io::Error,
+{
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => out.write_all(&buf[..len])?,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(on_read_error(e)),
+        }
+    }
+}
+
+/// Copy an opened operand to `out` byte for byte.
+pub fn copy(filename: &str, opened: Opened, out: &mut impl Sink) -> io::Result<()> {
+    match opened {
+        // `Input` names the file in its own errors
+        Opened::Decoded(mut input) => copy_buffered(&mut input, out, |e| e),
+        Opened::Plain(head, mut source) => {
+            out.write_all(&head)?;
+            if copy_kernel(&source, out)? {
+                return Ok(());
+            }
+            copy_buffered(&mut source, out, |e| {
+                io::Error::new(e.kind(), InputError::new(filename, e))
+            })
+        }
+    }
+}
+
+/// Returns false, having copied nothing, if the kernel cannot copy
+/// between these two.
+#[cfg(target_os = "linux")]
+fn copy_kernel(source: &Source, out: &mut impl Sink) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    match out.raw_fd()? {
+        Some(fd) => kernel::copy(source.as_raw_fd(), fd),
+        None => Ok(false),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_kernel(_source: &Source, _out: &mut impl Sink) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(target_os = "linux")]
+mod kernel {
+    use std::{io, mem, os::unix::io::RawFd, ptr};
+
+    /// The most to move in one system call
+    const CHUNK: usize = 1 << 30;
+
+    #[derive(Debug, Clone, Copy)]
+    enum Method {
+        /// file to file, possibly without moving any data at all
+        CopyFileRange,
+        /// file to anything
+        Sendfile,
+        /// pipe to anything, or anything to a pipe
+        Splice,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    enum Kind {
+        File,
+        Pipe,
+        Other,
+    }
+
+    /// What `fd` is. Regular files that claim to be empty are `Other`,
+    /// since the files of /proc and /sys have content but no size.
+    fn kind(fd: RawFd) -> io::Result<Kind> {
+        let mut stat: libc::stat = unsafe { mem::zeroed() };
+        if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(match stat.st_mode & libc::S_IFMT {
+            libc::S_IFREG if stat.st_size > 0 => Kind::File,
+            libc::S_IFIFO => Kind::Pipe,
+            _ => Kind::Other,
+        })
+    }
+
+    fn methods(from: Kind, to: Kind) -> &'static [Method] {
+        match (from, to) {
+            (Kind::File, Kind::File) => &[Method::CopyFileRange, Method::Sendfile],
+            (Kind::File, Kind::Pipe) => &[Method::Splice, Method::Sendfile],
+            (Kind::File, Kind::Other) => &[Method::Sendfile],
+            (Kind::Pipe, _) | (_, Kind::Pipe) => &[Method::Splice],
+            _ => &[],
+        }
+    }
+
+    fn call(method: Method, from: RawFd, to: RawFd) -> isize {
+        let null = ptr::null_mut();
+        unsafe {
+            match method {
+                Method::CopyFileRange => libc::copy_file_range(from, null, to, null, CHUNK, 0),
+                Method::Sendfile => libc::sendfile(to, from, null, CHUNK),
+                Method::Splice => libc::splice(from, null, to, null, CHUNK, libc::SPLICE_F_MOVE),
+            }
+        }
+    }
+
+    /// Copy all that is left from `from` to `to`. Returns false, having
+    /// copied nothing, if no system call can do it for these two.
+    pub fn copy(from: RawFd, to: RawFd) -> io::Result<bool> {
+        for &method in methods(kind(from)?, kind(to)?) {
+            let mut copied = false;
+            loop {
+                match call(method, from, to) {
+                    0 => return Ok(true),
+                    len if len > 0 => copied = true,
+                    _ => {
+                        let err = io::Error::last_os_error();
+                        match err.raw_os_error() {
+                            Some(libc::EINTR) => {}
+                            // not for these descriptors or this kernel, try
+                            // the next method
+                            Some(
+                                libc::EINVAL
+                                | libc::ENOSYS
+                                | libc::EXDEV
+                                | libc::EBADF
+                                | libc::EOPNOTSUPP
+                                | libc::EPERM,
+                            ) if !copied => break,
+                            _ => return Err(err),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,216 @@
+//! `--hex` dumps in the layout of xxd(1), and `--hex-reverse` to turn them
+//! back into bytes. Both work on fixed-size buffers, never whole lines of
+//! the input, so they run in constant memory on binaries of any size.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{CatrError, MyResult};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+/// A running dump, which carries a partial row over to the next input
+pub struct HexDump {
+    cols: usize,
+    group: usize,
+    offset: u64,
+    row: Vec<u8>,
+}
+
+impl HexDump {
+    /// `cols` bytes per row, in groups of `group` bytes (0 for one group)
+    pub fn new(cols: usize, group: usize) -> HexDump {
+        HexDump {
+            cols,
+            group: if group == 0 { cols } else { group },
+            offset: 0,
+            row: Vec::with_capacity(cols),
+        }
+    }
+
+    /// Dump everything `reader` has left
+    pub fn copy(&mut self, reader: &mut impl Read, out: &mut impl Write) -> io::Result<()> {
+        let mut buf = vec![0; BUF_SIZE];
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.write(&buf[..len], out)?;
+        }
+    }
+
+    fn write(&mut self, mut data: &[u8], out: &mut impl Write) -> io::Result<()> {
+        while !data.is_empty() {
+            let take = (self.cols - self.row.len()).min(data.len());
+            self.row.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.row.len() == self.cols {
+                self.write_row(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the last, partial row
+    pub fn finish(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.row.is_empty() {
+            Ok(())
+        } else {
+            self.write_row(out)
+        }
+    }
+
+    fn write_row(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let groups = self.cols.div_ceil(self.group);
+        let width = self.cols * 2 + groups - 1;
+
+        let mut line = format!("{:08x}: ", self.offset);
+        let start = line.len();
+        for (i, byte) in self.row.iter().enumerate() {
+            if i > 0 && i % self.group == 0 {
+                line.push(' ');
+            }
+            line.push_str(&format!("{:02x}", byte));
+        }
+        let pad = width - (line.len() - start);
+        line.extend(std::iter::repeat_n(' ', pad + 2));
+        line.extend(self.row.iter().map(|&byte| match byte {
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        }));
+        writeln!(out, "{}", line)?;
+
+        self.offset += self.row.len() as u64;
+        self.row.clear();
+        Ok(())
+    }
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+/// The bytes of one dump row: the offset, and the hex digits up to the two
+/// spaces in front of the text column
+fn parse_row(row: &[u8]) -> Option<(u64, Vec<u8>)> {
+    let colon = row.iter().position(|&c| c == b':')?;
+    let offset = std::str::from_utf8(&row[..colon]).ok()?.trim();
+    let offset = u64::from_str_radix(offset, 16).ok()?;
+
+    let mut bytes = vec![];
+    let mut rest = row[colon + 1..]
+        .strip_prefix(b" ")
+        .unwrap_or(&row[colon + 1..]);
+    loop {
+        match rest {
+            [b' ', b' ', ..] | [] | [b'\n' | b'\r', ..] => break,
+            [b' ', tail @ ..] => rest = tail,
+            [high, low, tail @ ..] => {
+                bytes.push(hex_value(*high)? << 4 | hex_value(*low)?);
+                rest = tail;
+            }
+            _ => return None,
+        }
+    }
+    Some((offset, bytes))
+}
+
+/// Turn the dump in `reader` back into bytes. `offset` is where the output
+/// stands, so that rows continue across inputs and gaps are filled with
+/// zeros.
+pub fn reverse(reader: &mut impl BufRead, offset: &mut u64, out: &mut impl Write) -> MyResult<()> {
+    let mut row = Vec::with_capacity(128);
+    let mut row_num = 0;
+    loop {
+        row.clear();
+        // rows are short, so one can never outgrow a fixed buffer
+        if reader
+            .by_ref()
+            .take(BUF_SIZE as u64)
+            .read_until(b'\n', &mut row)?
+            == 0
+        {
+            return Ok(());
+        }
+        row_num += 1;
+        if row.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let (row_offset, bytes) = match parse_row(&row) {
+            Some(parsed) if parsed.0 >= *offset => parsed,
+            _ => return Err(CatrError::InvalidHexDump(row_num)),
+        };
+        io::copy(&mut io::repeat(0).take(row_offset - *offset), out)?;
+        out.write_all(&bytes)?;
+        *offset = row_offset + bytes.len() as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_row, reverse, HexDump};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"The quick brown fox jumps\x00\x01\xff";
+
+    fn dump(data: &[u8], cols: usize, group: usize) -> String {
+        let mut out = vec![];
+        let mut hex = HexDump::new(cols, group);
+        // in two pieces, to cross a row
+        let (head, tail) = data.split_at(data.len().min(5));
+        hex.copy(&mut &head[..], &mut out).unwrap();
+        hex.copy(&mut &tail[..], &mut out).unwrap();
+        hex.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn undump(text: &str) -> Option<Vec<u8>> {
+        let mut out = vec![];
+        reverse(&mut Cursor::new(text), &mut 0, &mut out).ok()?;
+        Some(out)
+    }
+
+    #[test]
+    fn test_dump() {
+        assert_eq!(
+            dump(DATA, 16, 2),
+            "00000000: 5468 6520 7175 6963 6b20 6272 6f77 6e20  The quick brown \n\
+             00000010: 666f 7820 6a75 6d70 7300 01ff            fox jumps...\n"
+        );
+        assert_eq!(
+            dump(DATA, 10, 4),
+            "00000000: 54686520 71756963 6b20  The quick \n\
+             0000000a: 62726f77 6e20666f 7820  brown fox \n\
+             00000014: 6a756d70 730001ff       jumps...\n"
+        );
+        assert_eq!(
+            dump(&DATA[16..], 16, 0),
+            "00000000: 666f78206a756d70730001ff          fox jumps...\n"
+        );
+        assert_eq!(dump(b"", 16, 2), "");
+    }
+
+    #[test]
+    fn test_reverse() {
+        for (cols, group) in [(16, 2), (10, 4), (16, 0), (3, 1)] {
+            assert_eq!(undump(&dump(DATA, cols, group)).unwrap(), DATA);
+        }
+        // gaps are zeros, and hex digits in the text column are not data
+        assert_eq!(
+            undump("00000002: 6162  ab\n00000006: 63  cafe\n").unwrap(),
+            b"\0\0ab\0\0c"
+        );
+        assert_eq!(undump("00000004: 61\n00000000: 62\n"), None);
+        assert_eq!(undump("not a dump\n"), None);
+    }
+
+    #[test]
+    fn test_parse_row() {
+        assert_eq!(parse_row(b"10: 0a0b 0c\n"), Some((16, vec![10, 11, 12])));
+        assert_eq!(parse_row(b"10: 0a0"), None);
+        assert_eq!(parse_row(b"10: zz"), None);
+    }
+}
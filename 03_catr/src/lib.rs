@@ -1,99 +1,649 @@
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate, input,
+    output::Sink,
+    record,
+};
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+mod copy;
+mod hex;
+mod number;
+mod reverse;
+
+use hex::HexDump;
+use number::{Justify, Numbering, Style};
+
+type MyResult<T> = Result<T, CatrError>;
+
+#[derive(Debug)]
+pub enum CatrError {
+    InvalidStyle(String),
+    InvalidPattern(String),
+    InvalidNumber(&'static str, String),
+    InvalidHexDump(usize),
+    InvalidRange(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for CatrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatrError::InvalidStyle(style) => {
+                write!(f, "invalid body numbering style: '{}'", style)
+            }
+            CatrError::InvalidPattern(pattern) => write!(f, "Invalid pattern \"{}\"", pattern),
+            CatrError::InvalidNumber(what, val) => write!(f, "invalid {}: '{}'", what, val),
+            CatrError::InvalidHexDump(line) => write!(f, "invalid hex dump at line {}", line),
+            CatrError::InvalidRange(range) => write!(f, "invalid line range: '{}'", range),
+            CatrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CatrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CatrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CatrError {
+    fn from(e: io::Error) -> Self {
+        CatrError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    number_lines: bool,
-    number_nonblank_lines: bool,
+    numbering: Option<Numbering>,
+    squeeze_blank: bool,
+    show_nonprinting: bool,
+    show_ends: bool,
+    show_tabs: bool,
+    terminator: u8,
+    hex_dump: bool,
+    hex_reverse: bool,
+    hex_cols: usize,
+    hex_group: usize,
+    reverse: bool,
+    range: Option<Range>,
+}
+
+/// The 1-based, inclusive `--range` of records to print from each file
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Range {
+    start: usize,
+    end: Option<usize>,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("catr")
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILES: Opt = Opt::operand("files").multiple();
+    const NUMBER: Opt = Opt::flag("number")
+        .short('n')
+        .long("number")
+        .conflicts_with(&["number_nonblank", "body_numbering"]);
+    const NUMBER_NONBLANK: Opt = Opt::flag("number_nonblank")
+        .short('b')
+        .long("number-nonblank")
+        .conflicts_with(&["body_numbering"]);
+    const BODY_NUMBERING: Opt = Opt::option("body_numbering").long("body-numbering");
+    const NUMBER_WIDTH: Opt = Opt::option("number_width").long("number-width");
+    const NUMBER_SEPARATOR: Opt = Opt::option("number_separator").long("number-separator");
+    const STARTING_LINE_NUMBER: Opt =
+        Opt::option("starting_line_number").long("starting-line-number");
+    const LINE_INCREMENT: Opt = Opt::option("line_increment").long("line-increment");
+    const NUMBER_FORMAT: Opt = Opt::option("number_format").long("number-format");
+    const NUMBER_MODE: Opt = Opt::option("number_mode").long("number-mode");
+    const SQUEEZE_BLANK: Opt = Opt::flag("squeeze_blank").short('s').long("squeeze-blank");
+    const SHOW_ALL: Opt = Opt::flag("show_all").short('A').long("show-all");
+    const SHOW_NONPRINTING: Opt = Opt::flag("show_nonprinting")
+        .short('v')
+        .long("show-nonprinting");
+    const SHOW_ENDS: Opt = Opt::flag("show_ends").short('E').long("show-ends");
+    const SHOW_TABS: Opt = Opt::flag("show_tabs").short('T').long("show-tabs");
+    const HEX: Opt = Opt::flag("hex").long("hex").conflicts_with(&[
+        "number",
+        "number_nonblank",
+        "body_numbering",
+        "squeeze_blank",
+        "show_all",
+        "show_nonprinting",
+        "show_ends",
+        "show_tabs",
+        "hex_reverse",
+    ]);
+    const HEX_REVERSE: Opt = Opt::flag("hex_reverse")
+        .long("hex-reverse")
+        .conflicts_with(&[
+            "number",
+            "number_nonblank",
+            "body_numbering",
+            "squeeze_blank",
+            "show_all",
+            "show_nonprinting",
+            "show_ends",
+            "show_tabs",
+        ]);
+    const HEX_COLS: Opt = Opt::option("hex_cols").long("hex-cols");
+    const HEX_GROUP: Opt = Opt::option("hex_group").long("hex-group");
+    const REVERSE: Opt =
+        Opt::flag("reverse")
+            .long("reverse")
+            .conflicts_with(&["hex", "hex_reverse", "range"]);
+    const RANGE: Opt = Opt::option("range")
+        .long("range")
+        .conflicts_with(&["hex", "hex_reverse"]);
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("catr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Cat written in Rust")
+        .after_help("EXIT STATUS:\n    0 if every FILE was read, 1 if any FILE could not be opened")
         .arg(
-            Arg::with_name("files")
+            FILES
+                .arg()
                 .value_name("FILES")
                 .help("Input file(s)")
-                .multiple(true)
                 .default_value("-"),
         )
+        .arg(NUMBER.arg().help("Number lines"))
+        .arg(NUMBER_NONBLANK.arg().help("Number non-blank lines"))
+        .arg(BODY_NUMBERING.arg().value_name("STYLE").help(
+            "Number lines like nl: a|all, t|non-empty, n|none, or pREGEX \
+             for lines matching REGEX",
+        ))
+        .arg(
+            NUMBER_WIDTH
+                .arg()
+                .value_name("N")
+                .help("Width of line numbers [default: 6]"),
+        )
         .arg(
-            Arg::with_name("number")
-                .short("n")
-                .long("number")
-                .help("Number lines")
-                .takes_value(false)
-                .conflicts_with("number_nonblank"),
+            NUMBER_SEPARATOR
+                .arg()
+                .value_name("STRING")
+                .help("Text after line numbers [default: TAB]"),
         )
         .arg(
-            Arg::with_name("number_nonblank")
-                .short("b")
-                .long("number-nonblank")
-                .help("Number non-blank lines")
-                .takes_value(false),
+            STARTING_LINE_NUMBER
+                .arg()
+                .value_name("N")
+                .help("First line number [default: 1]")
+                .allow_hyphen_values(true),
         )
-        .get_matches();
+        .arg(
+            LINE_INCREMENT
+                .arg()
+                .value_name("N")
+                .help("Line number increment [default: 1]")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            NUMBER_FORMAT
+                .arg()
+                .value_name("FORMAT")
+                .help("Left justified (ln), right justified (rn) or zero-padded (rz)")
+                .possible_values(&["ln", "rn", "rz"])
+                .default_value("rn"),
+        )
+        .arg(
+            NUMBER_MODE
+                .arg()
+                .value_name("MODE")
+                .help("Restart line numbers for every file, or count on")
+                .possible_values(&["per-file", "continuous"])
+                .default_value("per-file"),
+        )
+        .arg(
+            SQUEEZE_BLANK
+                .arg()
+                .help("Suppress repeated empty output lines"),
+        )
+        .arg(SHOW_ALL.arg().help("Equivalent to -vET"))
+        .arg(
+            SHOW_NONPRINTING
+                .arg()
+                .help("Use ^ and M- notation, except for line delimiters and tabs"),
+        )
+        .arg(SHOW_ENDS.arg().help("Display $ at end of each line"))
+        .arg(SHOW_TABS.arg().help("Display TAB characters as ^I"))
+        .arg(HEX.arg().help("Dump the input in hex, like xxd"))
+        .arg(HEX_REVERSE.arg().help("Turn a hex dump back into binary"))
+        .arg(
+            HEX_COLS
+                .arg()
+                .value_name("N")
+                .help("Bytes per row of --hex, at most 256")
+                .default_value("16"),
+        )
+        .arg(
+            HEX_GROUP
+                .arg()
+                .value_name("N")
+                .help("Bytes per group of --hex, 0 for no grouping")
+                .default_value("2"),
+        )
+        .arg(
+            REVERSE
+                .arg()
+                .help("Print the lines of each file last first, like tac"),
+        )
+        .arg(
+            RANGE.arg().value_name("START:END").help(
+                "Print only lines START to END of each file, either of which may be left out",
+            ),
+        )
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [
+        FILES,
+        NUMBER,
+        NUMBER_NONBLANK,
+        BODY_NUMBERING,
+        NUMBER_WIDTH,
+        NUMBER_SEPARATOR,
+        STARTING_LINE_NUMBER,
+        LINE_INCREMENT,
+        NUMBER_FORMAT,
+        NUMBER_MODE,
+        SQUEEZE_BLANK,
+        SHOW_ALL,
+        SHOW_NONPRINTING,
+        SHOW_ENDS,
+        SHOW_TABS,
+        HEX,
+        HEX_REVERSE,
+        HEX_COLS,
+        HEX_GROUP,
+        REVERSE,
+        RANGE,
+        record::OPT,
+    ];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let files = matches.values_of_lossy("files").unwrap();
-    let number_lines = matches.is_present("number");
-    let number_nonblank_lines = matches.is_present("number_nonblank");
+    let style = if matches.is_present("number") {
+        Some(Style::All)
+    } else if matches.is_present("number_nonblank") {
+        Some(Style::NonEmpty)
+    } else {
+        None
+    };
+    let nl_style = matches
+        .value_of("body_numbering")
+        .map(Style::parse)
+        .transpose()?;
+    let numbering = match (style, nl_style) {
+        (Some(style), _) => Some(Numbering::cat(style)),
+        (None, Some(style)) => Some(Numbering {
+            pad: true,
+            ..Numbering::cat(style)
+        }),
+        (None, None) => None,
+    };
+    let width = match parse_number(&matches, "number_width", "line number field width")? {
+        Some(width) if width == 0 || width > number::MAX_WIDTH => {
+            let width = matches.value_of("number_width").unwrap().to_string();
+            return Err(CatrError::InvalidNumber("line number field width", width));
+        }
+        width => width,
+    };
+    let start = parse_number(&matches, "starting_line_number", "starting line number")?;
+    let increment = parse_number(&matches, "line_increment", "line number increment")?;
+    let numbering = numbering.map(|numbering| Numbering {
+        justify: Justify::parse(matches.value_of("number_format").unwrap()).unwrap(),
+        width: width.unwrap_or(numbering.width),
+        separator: matches
+            .value_of("number_separator")
+            .map_or(numbering.separator, String::from),
+        start: start.unwrap_or(numbering.start),
+        increment: increment.unwrap_or(numbering.increment),
+        per_file: matches.value_of("number_mode") == Some("per-file"),
+        ..numbering
+    });
+    let squeeze_blank = matches.is_present("squeeze_blank");
+    let show_all = matches.is_present("show_all");
+    let show_nonprinting = show_all || matches.is_present("show_nonprinting");
+    let show_ends = show_all || matches.is_present("show_ends");
+    let show_tabs = show_all || matches.is_present("show_tabs");
+    let terminator = record::terminator(&matches);
+    let hex_cols = match parse_number(&matches, "hex_cols", "hex column count")? {
+        Some(cols @ 1..=256) => cols,
+        _ => {
+            let cols = matches.value_of("hex_cols").unwrap().to_string();
+            return Err(CatrError::InvalidNumber("hex column count", cols));
+        }
+    };
+    let hex_group = parse_number(&matches, "hex_group", "hex group size")?.unwrap();
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         files,
-        number_lines,
-        number_nonblank_lines,
-    })
+        numbering,
+        squeeze_blank,
+        show_nonprinting,
+        show_ends,
+        show_tabs,
+        terminator,
+        hex_dump: matches.is_present("hex"),
+        hex_reverse: matches.is_present("hex_reverse"),
+        hex_cols,
+        hex_group,
+        reverse: matches.is_present("reverse"),
+        range: matches.value_of("range").map(parse_range).transpose()?,
+    }))
+}
+
+/// Parse `START:END`, `START:`, `:END` or a single line number
+fn parse_range(range: &str) -> MyResult<Range> {
+    let bad = || CatrError::InvalidRange(range.to_string());
+    let number = |val: &str| match val.parse() {
+        Ok(0) | Err(_) => Err(bad()),
+        Ok(num) => Ok(num),
+    };
+    let (start, end) = match range.split_once(':') {
+        Some((start, end)) => (start, end),
+        None => {
+            let line = number(range)?;
+            return Ok(Range {
+                start: line,
+                end: Some(line),
+            });
+        }
+    };
+    let start = match start {
+        "" => 1,
+        start => number(start)?,
+    };
+    let end = match end {
+        "" => None,
+        end => match number(end)? {
+            end if end < start => return Err(bad()),
+            end => Some(end),
+        },
+    };
+    Ok(Range { start, end })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+/// The value of the option `name`, if given
+fn parse_number<T: FromStr>(
+    matches: &clap::ArgMatches,
+    name: &str,
+    what: &'static str,
+) -> MyResult<Option<T>> {
+    matches
+        .value_of(name)
+        .map(|val| {
+            val.parse()
+                .map_err(|_| CatrError::InvalidNumber(what, val.to_string()))
+        })
+        .transpose()
+}
+
+/// Append `byte` to `out` in the ^ and M- notation of `cat -v`
+fn push_visible(out: &mut Vec<u8>, byte: u8) {
+    let byte = if byte >= 0x80 {
+        out.extend_from_slice(b"M-");
+        byte - 0x80
+    } else {
+        byte
+    };
+    match byte {
+        0x00..=0x1f => out.extend_from_slice(&[b'^', byte + 0x40]),
+        0x7f => out.extend_from_slice(b"^?"),
+        _ => out.push(byte),
+    }
+}
+
+/// The bytes of a record without its terminator, as -v and -T show them
+fn visible(config: &Config, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    for &byte in body {
+        match byte {
+            b'\t' if config.show_tabs => out.extend_from_slice(b"^I"),
+            b'\t' | b'\n' => out.push(byte),
+            _ if config.show_nonprinting => push_visible(&mut out, byte),
+            _ => out.push(byte),
+        }
+    }
+    out
+}
+
+/// Prints records with numbering and the other per-line options, keeping
+/// what carries over from one record, and file, to the next
+struct Printer<'a> {
+    config: &'a Config,
+    /// The next line number, or `None` once counting has passed `i64::MAX`
+    line_num: Option<i64>,
+    last_blank: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(config: &'a Config) -> Printer<'a> {
+        Printer {
+            config,
+            line_num: config.numbering.as_ref().map(|numbering| numbering.start),
+            // like GNU cat, a run of blank lines continues into the next file
+            last_blank: false,
+        }
+    }
+
+    fn start_file(&mut self) {
+        if let Some(numbering) = self.config.numbering.as_ref().filter(|n| n.per_file) {
+            self.line_num = Some(numbering.start);
+        }
+    }
+
+    /// Print the records of `file` in the configured range, reading each
+    /// one into `record`
+    fn print_range(
+        &mut self,
+        file: &mut impl BufRead,
+        record: &mut Vec<u8>,
+        out: &mut impl Sink,
+    ) -> io::Result<()> {
+        let terminator = self.config.terminator;
+        let range = self.config.range.unwrap_or(Range {
+            start: 1,
+            end: None,
+        });
+        let mut record_num = 0;
+        loop {
+            record.clear();
+            if file.read_until(terminator, record)? == 0 {
+                return Ok(());
+            }
+            record_num += 1;
+            if record_num < range.start {
+                continue;
+            }
+            self.print(record, out)?;
+            // stop reading once past the range
+            if range.end == Some(record_num) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Print a record as read, with its terminator if it has one
+    fn print(&mut self, record: &[u8], out: &mut impl Sink) -> io::Result<()> {
+        let config = self.config;
+        let (body, terminated) = match record.split_last() {
+            Some((&last, body)) if last == config.terminator => (body, true),
+            _ => (record, false),
+        };
+
+        let blank = body.is_empty();
+        if config.squeeze_blank && blank && self.last_blank {
+            return Ok(());
+        }
+        self.last_blank = blank;
+
+        if let Some(numbering) = &config.numbering {
+            numbering.write(out, body, &mut self.line_num)?;
+        }
+        if config.show_nonprinting || config.show_tabs {
+            out.write_all(&visible(config, body))?;
+        } else {
+            out.write_all(body)?;
+        }
+        if terminated {
+            if config.show_ends {
+                out.write_all(b"$")?;
+            }
+            out.write_all(&[config.terminator])?;
+        }
+        Ok(())
     }
 }
 
-// will not compile
-// fn open2(filename: &str) -> MyResult<dyn BufRead> {
-//     match filename {
-//         "-" => Ok(BufReader::new(io::stdin())),
-//         _ => Ok(BufReader::new(File::open(filename)?)),
-//     }
-// }
-
-pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to open {}: {}", filename, err),
-            Ok(file) => {
-                let mut line_num = 0;
-                for line in file.lines() {
-                    let line = line?;
-
-                    line_num += 1;
-
-                    if config.number_lines {
-                        println!("{:>6}\t{}", line_num, line);
-                    } else if config.number_nonblank_lines {
-                        if line.is_empty() {
-                            line_num -= 1;
-                            println!();
-                        } else {
-                            println!("{:>6}\t{}", line_num, line);
-                        }
-                    } else {
-                        println!("{}", line);
-                    }
+/// Returns the exit status: 1 if any file could not be opened or read.
+pub fn run(config: Config, out: &mut impl Sink) -> MyResult<i32> {
+    let plain = config.numbering.is_none()
+        && !config.squeeze_blank
+        && !config.show_nonprinting
+        && !config.show_tabs
+        && !config.show_ends
+        && !config.hex_dump
+        && !config.hex_reverse
+        && !config.reverse
+        && config.range.is_none();
+    let mut status = 0;
+    let mut record = vec![];
+    let mut printer = Printer::new(&config);
+    let mut hex = HexDump::new(config.hex_cols, config.hex_group);
+    let mut hex_offset = 0;
+    for filename in &config.files {
+        let printed = if plain || config.reverse {
+            match input::open_raw(filename) {
+                Err(err) => {
+                    eprintln!("{}", err);
+                    status = 1;
+                    continue;
                 }
+                Ok(opened) if plain => copy::copy(filename, opened, out).map_err(CatrError::Io),
+                Ok(opened) => {
+                    printer.start_file();
+                    reverse::records(filename, opened, config.terminator, &mut |record| {
+                        printer.print(record, out)
+                    })
+                    .map_err(CatrError::Io)
+                }
+            }
+        } else {
+            match input::open(filename) {
+                Err(err) => {
+                    eprintln!("{}", err);
+                    status = 1;
+                    continue;
+                }
+                // a dump covers all files as one stream, like `cat FILES | xxd`
+                Ok(mut file) if config.hex_dump => hex.copy(&mut file, out).map_err(CatrError::Io),
+                Ok(mut file) if config.hex_reverse => hex::reverse(&mut file, &mut hex_offset, out),
+                Ok(mut file) => {
+                    printer.start_file();
+                    printer
+                        .print_range(&mut file, &mut record, out)
+                        .map_err(CatrError::Io)
+                }
+            }
+        };
+        match printed {
+            // the error names the operand; go on to the next one
+            Err(CatrError::Io(err)) if input::is_read_error(&err) => {
+                eprintln!("{}", err);
+                status = 1;
             }
+            printed => printed?,
+        }
+    }
+
+    if config.hex_dump {
+        hex.finish(out)?;
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_range, visible, Config, Range};
+
+    fn config(show_nonprinting: bool, show_tabs: bool) -> Config {
+        Config {
+            files: vec![],
+            numbering: None,
+            squeeze_blank: false,
+            show_nonprinting,
+            show_ends: false,
+            show_tabs,
+            terminator: b'\n',
+            hex_dump: false,
+            hex_reverse: false,
+            hex_cols: 16,
+            hex_group: 2,
+            reverse: false,
+            range: None,
         }
     }
 
-    Ok(())
+    #[test]
+    fn test_visible() {
+        let body = b"a\tb\r\x00\x1b[0m\x7f";
+        assert_eq!(
+            visible(&config(false, true), body),
+            b"a^Ib\r\x00\x1b[0m\x7f"
+        );
+        assert_eq!(visible(&config(true, false), body), b"a\tb^M^@^[[0m^?");
+        assert_eq!(visible(&config(true, true), body), b"a^Ib^M^@^[[0m^?");
+    }
+
+    #[test]
+    fn test_visible_high_bytes() {
+        let nonprinting = config(true, false);
+        assert_eq!(
+            visible(&nonprinting, b"\x80\x9b\xa0\xe9\xff"),
+            b"M-^@M-^[M- M-iM-^?"
+        );
+        assert_eq!(visible(&nonprinting, "é".as_bytes()), b"M-CM-)");
+        // with -z a newline can be inside a record, and is left alone
+        assert_eq!(visible(&nonprinting, b"a\nb"), b"a\nb");
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let range = |start, end| Ok::<_, String>(Range { start, end });
+        let parse = |text| parse_range(text).map_err(|e| e.to_string());
+        assert_eq!(parse("3:7"), range(3, Some(7)));
+        assert_eq!(parse("3:"), range(3, None));
+        assert_eq!(parse(":7"), range(1, Some(7)));
+        assert_eq!(parse(":"), range(1, None));
+        assert_eq!(parse("4"), range(4, Some(4)));
+        assert_eq!(parse("4:4"), range(4, Some(4)));
+        for bad in ["0:3", "7:3", "a:b", "1:2:3", ""] {
+            assert_eq!(parse(bad), Err(format!("invalid line range: '{}'", bad)));
+        }
+    }
 }
@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = catr::get_args().and_then(catr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| catr::get_args().and_then(|parsed| parsed.run(out, catr::run)),
+        1,
+    ));
 }
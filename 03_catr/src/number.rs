@@ -0,0 +1,211 @@
+//! Line numbering in the manner of nl(1): which lines get a number, and
+//! how the number is laid out.
+
+use std::io::{self, Write};
+
+use regex::bytes::Regex;
+
+use crate::{CatrError, MyResult};
+
+/// Which lines are numbered
+#[derive(Debug)]
+pub enum Style {
+    All,
+    NonEmpty,
+    None,
+    Regex(Regex),
+}
+
+impl Style {
+    /// Parse nl's `a`, `t`, `n` and `pREGEX`, or the spelled out names
+    pub fn parse(style: &str) -> MyResult<Style> {
+        match style {
+            "a" | "all" => Ok(Style::All),
+            "t" | "non-empty" => Ok(Style::NonEmpty),
+            "n" | "none" => Ok(Style::None),
+            _ => match style.strip_prefix('p') {
+                Some(pattern) => Regex::new(pattern)
+                    .map(Style::Regex)
+                    .map_err(|_| CatrError::InvalidPattern(pattern.to_string())),
+                None => Err(CatrError::InvalidStyle(style.to_string())),
+            },
+        }
+    }
+
+    fn matches(&self, body: &[u8]) -> bool {
+        match self {
+            Style::All => true,
+            Style::NonEmpty => !body.is_empty(),
+            Style::None => false,
+            Style::Regex(re) => re.is_match(body),
+        }
+    }
+}
+
+/// How a number fills its field: nl's `ln`, `rn` and `rz`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Justify {
+    Left,
+    Right,
+    RightZero,
+}
+
+impl Justify {
+    pub fn parse(format: &str) -> Option<Justify> {
+        match format {
+            "ln" => Some(Justify::Left),
+            "rn" => Some(Justify::Right),
+            "rz" => Some(Justify::RightZero),
+            _ => None,
+        }
+    }
+}
+
+/// The widest number field `--number-width` accepts
+pub const MAX_WIDTH: usize = 1024;
+
+#[derive(Debug)]
+pub struct Numbering {
+    pub style: Style,
+    pub justify: Justify,
+    pub width: usize,
+    pub separator: String,
+    pub start: i64,
+    pub increment: i64,
+    /// Restart at `start` for every file, rather than counting on
+    pub per_file: bool,
+    /// Fill the number field of unnumbered lines with blanks, as nl does
+    pub pad: bool,
+}
+
+impl Numbering {
+    /// The numbering of `cat -n`, or of `cat -b` with `Style::NonEmpty`
+    pub fn cat(style: Style) -> Numbering {
+        Numbering {
+            style,
+            justify: Justify::Right,
+            width: 6,
+            separator: "\t".to_string(),
+            start: 1,
+            increment: 1,
+            per_file: true,
+            pad: false,
+        }
+    }
+
+    /// Write the number field for a record whose contents are `body`, and
+    /// advance `line_num` if the record was numbered. Numbering a record
+    /// after `i64::MAX` fails.
+    pub fn write(
+        &self,
+        out: &mut impl Write,
+        body: &[u8],
+        line_num: &mut Option<i64>,
+    ) -> io::Result<()> {
+        let width = self.width;
+        if self.style.matches(body) {
+            let num = line_num.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "line number overflow")
+            })?;
+            match self.justify {
+                Justify::Left => write!(out, "{:<width$}", num)?,
+                Justify::Right => write!(out, "{:>width$}", num)?,
+                Justify::RightZero => write!(out, "{:0width$}", num)?,
+            }
+            *line_num = num.checked_add(self.increment);
+            write!(out, "{}", self.separator)
+        } else if self.pad {
+            let width = width + self.separator.chars().count();
+            write!(out, "{:width$}", "")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Justify, Numbering, Style};
+
+    fn numbered(numbering: &Numbering, bodies: &[&str], line_num: i64) -> String {
+        let mut line_num = Some(line_num);
+        let mut out = vec![];
+        for body in bodies {
+            numbering
+                .write(&mut out, body.as_bytes(), &mut line_num)
+                .unwrap();
+            out.extend_from_slice(body.as_bytes());
+            out.push(b'\n');
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_cat() {
+        let bodies = ["a", "", "b"];
+        let numbering = Numbering::cat(Style::All);
+        assert_eq!(
+            numbered(&numbering, &bodies, 1),
+            "     1\ta\n     2\t\n     3\tb\n"
+        );
+        let numbering = Numbering::cat(Style::NonEmpty);
+        assert_eq!(numbered(&numbering, &bodies, 1), "     1\ta\n\n     2\tb\n");
+    }
+
+    #[test]
+    fn test_layout() {
+        let numbering = Numbering {
+            style: Style::parse("p^b").unwrap(),
+            justify: Justify::RightZero,
+            width: 3,
+            separator: "|".to_string(),
+            start: 10,
+            increment: 5,
+            per_file: false,
+            pad: true,
+        };
+        assert_eq!(
+            numbered(&numbering, &["a", "", "b", "bb"], 10),
+            "    a\n    \n010|b\n015|bb\n"
+        );
+        let numbering = Numbering {
+            justify: Justify::Left,
+            pad: false,
+            ..numbering
+        };
+        assert_eq!(numbered(&numbering, &["b"], 7), "7  |b\n");
+        let numbering = Numbering {
+            justify: Justify::RightZero,
+            ..numbering
+        };
+        assert_eq!(numbered(&numbering, &["b"], -2), "-02|b\n");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let numbering = Numbering::cat(Style::All);
+        let mut line_num = Some(i64::MAX);
+        let mut out = vec![];
+        numbering.write(&mut out, b"a", &mut line_num).unwrap();
+        assert_eq!(out, format!("{}\t", i64::MAX).as_bytes());
+        assert_eq!(line_num, None);
+        let err = numbering.write(&mut out, b"b", &mut line_num).unwrap_err();
+        assert_eq!(err.to_string(), "line number overflow");
+    }
+
+    #[test]
+    fn test_style_parse() {
+        assert!(matches!(Style::parse("a"), Ok(Style::All)));
+        assert!(matches!(Style::parse("non-empty"), Ok(Style::NonEmpty)));
+        assert!(matches!(Style::parse("n"), Ok(Style::None)));
+        assert!(matches!(Style::parse("p[0-9]"), Ok(Style::Regex(_))));
+        assert_eq!(
+            Style::parse("p(").unwrap_err().to_string(),
+            "Invalid pattern \"(\""
+        );
+        assert_eq!(
+            Style::parse("x").unwrap_err().to_string(),
+            "invalid body numbering style: 'x'"
+        );
+    }
+}
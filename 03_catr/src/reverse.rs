@@ -0,0 +1,210 @@
+//! `--reverse`: records last first, like tac(1). Files are read backwards
+//! a block at a time; anything that cannot seek is read into memory.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use clr_common::input::{InputError, Opened, Source};
+
+const BLOCK_SIZE: u64 = 64 * 1024;
+
+/// Pass the complete records at the end of `chunk` to `emit`, last first,
+/// and return where the earliest one, which may be incomplete, starts.
+fn emit_complete<F>(chunk: &[u8], terminator: u8, emit: &mut F) -> io::Result<usize>
+where
+    F: FnMut(&[u8]) -> io::Result<()>,
+{
+    let mut end = chunk.len();
+    for pos in (0..chunk.len()).rev() {
+        // the terminator at `end - 1` is the end of the record being built
+        if chunk[pos] == terminator && pos + 1 < end {
+            emit(&chunk[pos + 1..end])?;
+            end = pos + 1;
+        }
+    }
+    Ok(end)
+}
+
+fn reverse_buffered<F>(data: &[u8], terminator: u8, emit: &mut F) -> io::Result<()>
+where
+    F: FnMut(&[u8]) -> io::Result<()>,
+{
+    match emit_complete(data, terminator, emit)? {
+        0 => Ok(()),
+        end => emit(&data[..end]),
+    }
+}
+
+/// Read `file` backwards a block at a time, calling `on_read_error` on what
+/// failed to seek or read
+fn reverse_file<F, E>(
+    file: &mut File,
+    terminator: u8,
+    emit: &mut F,
+    on_read_error: E,
+) -> io::Result<()>
+where
+    F: FnMut(&[u8]) -> io::Result<()>,
+    E: Fn(io::Error) -> io::Error,
+{
+    let mut pos = file.seek(SeekFrom::End(0)).map_err(&on_read_error)?;
+    // the blocks, last read first, of the record that runs on past the
+    // block being scanned
+    let mut partial: Vec<Vec<u8>> = vec![];
+    let mut chunk = vec![];
+    while pos > 0 {
+        let len = pos.min(BLOCK_SIZE);
+        pos -= len;
+        chunk.resize(len as usize, 0);
+        file.seek(SeekFrom::Start(pos))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .map_err(&on_read_error)?;
+
+        let mut end = chunk.len();
+        for i in (0..chunk.len()).rev() {
+            if chunk[i] != terminator {
+                continue;
+            }
+            // the terminator at `i` ends the record before the one that
+            // starts right after it
+            if partial.is_empty() {
+                if i + 1 < end {
+                    emit(&chunk[i + 1..end])?;
+                }
+            } else {
+                let mut record = chunk[i + 1..end].to_vec();
+                partial
+                    .drain(..)
+                    .rev()
+                    .for_each(|block| record.extend(block));
+                emit(&record)?;
+            }
+            end = i + 1;
+        }
+        if end > 0 {
+            partial.push(chunk[..end].to_vec());
+        }
+    }
+    if partial.is_empty() {
+        Ok(())
+    } else {
+        emit(&partial.into_iter().rev().flatten().collect::<Vec<u8>>())
+    }
+}
+
+/// True if `file` can be read backwards: a regular file that says how
+/// long it is. Pipes, devices and the files of /proc and /sys cannot.
+fn seekable(file: &File) -> io::Result<bool> {
+    let metadata = file.metadata()?;
+    Ok(metadata.is_file() && metadata.len() > 0)
+}
+
+/// Pass every record of `opened`, terminator included, to `emit` in reverse
+/// order. As with tac, a final record without a terminator comes out first
+/// and runs into the next one. Errors reading `filename` name it.
+pub fn records<F>(filename: &str, opened: Opened, terminator: u8, emit: &mut F) -> io::Result<()>
+where
+    F: FnMut(&[u8]) -> io::Result<()>,
+{
+    let named = |e: io::Error| io::Error::new(e.kind(), InputError::new(filename, e));
+    let mut data = match opened {
+        Opened::Plain(_, Source::File(mut file)) if seekable(&file).map_err(named)? => {
+            return reverse_file(&mut file, terminator, emit, named);
+        }
+        Opened::Plain(head, mut source) => {
+            let mut data = head;
+            source.read_to_end(&mut data).map_err(named)?;
+            data
+        }
+        // `Input` names the file in its own errors
+        Opened::Decoded(mut input) => {
+            let mut data = vec![];
+            input.read_to_end(&mut data)?;
+            data
+        }
+    };
+    data.shrink_to_fit();
+    reverse_buffered(&data, terminator, emit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reverse_buffered, reverse_file, BLOCK_SIZE};
+    use std::io::Write;
+
+    fn collect(data: &[u8], terminator: u8) -> Vec<u8> {
+        let mut out = vec![];
+        reverse_buffered(data, terminator, &mut |record| {
+            out.extend_from_slice(record);
+            Ok(())
+        })
+        .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_reverse_buffered() {
+        assert_eq!(collect(b"a\nb\nc\n", b'\n'), b"c\nb\na\n");
+        assert_eq!(collect(b"a\n\nb", b'\n'), b"b\na\n");
+        assert_eq!(collect(b"\n\n", b'\n'), b"\n\n");
+        assert_eq!(collect(b"a\0b\nc\0", b'\0'), b"b\nc\0a\0");
+        assert_eq!(collect(b"", b'\n'), b"");
+    }
+
+    #[test]
+    fn test_reverse_file() {
+        // long records, so they cross blocks
+        let line = "x".repeat(BLOCK_SIZE as usize / 3);
+        let lines: Vec<String> = (0..10).map(|i| format!("{}{}\n", i, line)).collect();
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(lines.concat().as_bytes()).unwrap();
+
+        let mut out = vec![];
+        reverse_file(
+            &mut file,
+            b'\n',
+            &mut |record| {
+                out.push(String::from_utf8(record.to_vec()).unwrap());
+                Ok(())
+            },
+            |e| e,
+        )
+        .unwrap();
+        let expected: Vec<String> = lines.into_iter().rev().collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_reverse_file_long_records() {
+        // records of many blocks, some ending right at a block boundary
+        let block = BLOCK_SIZE as usize;
+        let records = [
+            "a".repeat(block * 5 + 7) + "\n",
+            "b".repeat(block - 1) + "\n",
+            "\n".to_string(),
+            "c".repeat(block * 3),
+        ];
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(records.concat().as_bytes()).unwrap();
+
+        let mut out = vec![];
+        reverse_file(
+            &mut file,
+            b'\n',
+            &mut |record| {
+                out.push(record.to_vec());
+                Ok(())
+            },
+            |e| e,
+        )
+        .unwrap();
+        let expected: Vec<Vec<u8>> = records
+            .iter()
+            .rev()
+            .map(|r| r.clone().into_bytes())
+            .collect();
+        assert_eq!(out, expected);
+    }
+}
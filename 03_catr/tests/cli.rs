@@ -3,6 +3,8 @@ use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::error::Error;
 use std::fs;
+use std::io::Write;
+use std::process::{self, Stdio};
 
 type TestResult = Result<(), Box<dyn Error>>;
 
@@ -47,11 +49,54 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn skips_unreadable_file() -> TestResult {
+    let truncated = "tests/inputs/truncated.txt.gz";
+    let error = format!("{}: incomplete deflate stream\n", truncated);
+    let fox = "The quick brown fox jumps over the lazy dog.\n";
+    for (args, stdout) in [
+        (vec![truncated, FOX], format!("The quick brown fox{}", fox)),
+        (vec!["-n", truncated, FOX], format!("     1\t{}", fox)),
+        (vec!["--reverse", truncated, FOX], fox.to_string()),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .code(1)
+            .stderr(error.clone())
+            .stdout(stdout);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn broken_pipe() -> TestResult {
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_catr"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // The reader goes away before catr writes anything
+    drop(child.stdout.take());
+    if let Some(mut stdin) = child.stdin.take() {
+        // catr may exit before reading all of it
+        let _ = stdin.write_all("The quick brown fox\n".repeat(100_000).as_bytes());
+    }
+
+    let output = child.wait_with_output()?;
+    assert_eq!(output.status.code(), Some(141));
+    assert_eq!(String::from_utf8(output.stderr)?, "");
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
@@ -198,3 +243,354 @@ fn all_n() -> TestResult {
 fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn fox_compressed() -> TestResult {
+    for ext in &["gz", "bz2", "xz", "zst"] {
+        run(&[&format!("{}.{}", FOX, ext)], "tests/expected/fox.txt.out")?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_compressed_n() -> TestResult {
+    run(
+        &["-n", &format!("{}.gz", FOX)],
+        "tests/expected/fox.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_compressed_stdin() -> TestResult {
+    let input = fs::read(format!("{}.gz", FOX))?;
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-z"])
+        .write_stdin("a\nb\0\0c")
+        .assert()
+        .success()
+        .stdout("     1\ta\nb\0     2\t\0     3\tc");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn show_all() -> TestResult {
+    let input = b"tab\there\r\n\x1b[1mbold\x7f\n\xe9t\xc3\xa9\n\nend".to_vec();
+    Command::cargo_bin(PRG)?
+        .arg("-A")
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout("tab^Ihere^M$\n^[[1mbold^?$\nM-itM-CM-)$\n$\nend");
+    Command::cargo_bin(PRG)?
+        .args(["-vE"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout("tab\there^M$\n^[[1mbold^?$\nM-itM-CM-)$\n$\nend");
+    Command::cargo_bin(PRG)?
+        .args(["-bT"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(
+            &b"     1\ttab^Ihere\r\n     2\t\x1b[1mbold\x7f\n     3\t\xe9t\xc3\xa9\n\n     4\tend"
+                [..],
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("-n")
+        .write_stdin(b"\xff\xfe\n".to_vec())
+        .assert()
+        .success()
+        .stdout(&b"     1\t\xff\xfe\n"[..]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_blank() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-sn"])
+        .write_stdin("a\n\n\n\nb\n\nc\n\n")
+        .assert()
+        .success()
+        .stdout("     1\ta\n     2\t\n     3\tb\n     4\t\n     5\tc\n     6\t\n");
+    Command::cargo_bin(PRG)?
+        .args(["-sb"])
+        .write_stdin("\n\na\n\n\nb\n")
+        .assert()
+        .success()
+        .stdout("\n     1\ta\n\n     2\tb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_blank_across_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-s", "-", EMPTY, "-"])
+        .write_stdin("a\n\n\n")
+        .assert()
+        .success()
+        .stdout("a\n\n");
+    Command::cargo_bin(PRG)?
+        .args(["-s", SPIDERS, "-", SPIDERS])
+        .write_stdin("\n\n\n")
+        .assert()
+        .success()
+        .stdout(format!("{0}\n{0}", fs::read_to_string(SPIDERS)?));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn number_mode() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-mode=continuous", FOX, SPIDERS])
+        .assert()
+        .success()
+        .stdout(
+            "     1\tThe quick brown fox jumps over the lazy dog.\n\
+             \x20    2\tDon't worry, spiders,\n\
+             \x20    3\tI keep house\n\
+             \x20    4\tcasually.\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn body_numbering() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--body-numbering=t"])
+        .write_stdin("a\n\nb\n")
+        .assert()
+        .success()
+        .stdout("     1\ta\n       \n     2\tb\n");
+    Command::cargo_bin(PRG)?
+        .args([
+            "--body-numbering=p^b",
+            "--starting-line-number=10",
+            "--line-increment=5",
+            "--number-width=3",
+            "--number-separator=|",
+            "--number-format=rz",
+        ])
+        .write_stdin("a\n\nb\nbb\n")
+        .assert()
+        .success()
+        .stdout("    a\n    \n010|b\n015|bb\n");
+    Command::cargo_bin(PRG)?
+        .args([
+            "-b",
+            "--number-format=ln",
+            "--number-width=2",
+            "--number-separator= ",
+        ])
+        .write_stdin("a\n\nb\n")
+        .assert()
+        .success()
+        .stdout("1  a\n\n2  b\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_numbering() -> TestResult {
+    for (args, error) in [
+        (
+            ["--body-numbering", "x"],
+            "invalid body numbering style: 'x'",
+        ),
+        (["--body-numbering", "p("], "Invalid pattern \"(\""),
+        (
+            ["--number-width", "0"],
+            "invalid line number field width: '0'",
+        ),
+        (
+            ["--number-width", "99999999999"],
+            "invalid line number field width: '99999999999'",
+        ),
+        (
+            ["--line-increment", "one"],
+            "invalid line number increment: 'one'",
+        ),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(format!("{}\n", error));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hex() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hex", FOX, "-"])
+        .write_stdin(b"\x00\x01\xff".to_vec())
+        .assert()
+        .success()
+        .stdout(
+            "00000000: 5468 6520 7175 6963 6b20 6272 6f77 6e20  The quick brown \n\
+             00000010: 666f 7820 6a75 6d70 7320 6f76 6572 2074  fox jumps over t\n\
+             00000020: 6865 206c 617a 7920 646f 672e 0a00 01ff  he lazy dog.....\n",
+        );
+    Command::cargo_bin(PRG)?
+        .args(["--hex", "--hex-cols=8", "--hex-group=4", FOX])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "00000000: 54686520 71756963  The quic\n\
+             00000008: 6b206272 6f776e20  k brown \n",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_reverse() -> TestResult {
+    let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--hex", "--hex-cols=7", "-"])
+        .write_stdin(data.clone())
+        .assert()
+        .success();
+    Command::cargo_bin(PRG)?
+        .arg("--hex-reverse")
+        .write_stdin(cmd.get_output().stdout.clone())
+        .assert()
+        .success()
+        .stdout(data);
+
+    Command::cargo_bin(PRG)?
+        .arg("--hex-reverse")
+        .write_stdin("00000000: 4142  AB\ngarbage\n")
+        .assert()
+        .failure()
+        .stdout("AB")
+        .stderr("invalid hex dump at line 2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_passthrough() -> TestResult {
+    let data: Vec<u8> = (0..=255).cycle().take(300_000).collect();
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("data.bin");
+    fs::write(&input, &data)?;
+    let input = input.to_str().unwrap();
+
+    // a pipe from stdin, and a file both in the middle of the output
+    let expected: Vec<u8> = [&data[..], b"x\xff", &data[..], &data[..]].concat();
+    Command::cargo_bin(PRG)?
+        .args([input, "-", input, input])
+        .write_stdin(b"x\xff".to_vec())
+        .assert()
+        .success()
+        .stdout(expected.clone());
+
+    // straight into a file, where the kernel can copy between files
+    let output = dir.path().join("out.bin");
+    let status = process::Command::new(env!("CARGO_BIN_EXE_catr"))
+        .args([input, "-", input, input])
+        .stdin(fs::File::open(FOX)?)
+        .stdout(fs::File::create(&output)?)
+        .status()?;
+    assert!(status.success());
+    let fox = fs::read(FOX)?;
+    assert_eq!(
+        fs::read(&output)?,
+        [&data[..], &fox, &data[..], &data[..]].concat()
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", SPIDERS, "-", EMPTY])
+        .write_stdin("one\ntwo\nno newline")
+        .assert()
+        .success()
+        .stdout("casually.\nI keep house\nDon't worry, spiders,\nno newlinetwo\none\n");
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", "-n", "tests/inputs/fox.txt.gz", SPIDERS])
+        .assert()
+        .success()
+        .stdout(
+            "     1\tThe quick brown fox jumps over the lazy dog.\n\
+             \x20    1\tcasually.\n\
+             \x20    2\tI keep house\n\
+             \x20    3\tDon't worry, spiders,\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(target_os = "linux")]
+#[test]
+fn reverse_unseekable() -> TestResult {
+    // a pipe and a /proc file, opened by name, are read into memory
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", "/dev/stdin", SPIDERS])
+        .write_stdin("a\nb\n")
+        .assert()
+        .success()
+        .stdout("b\na\ncasually.\nI keep house\nDon't worry, spiders,\n");
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", "/proc/self/status"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("nonvoluntary_ctxt_switches:"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn range() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--range", "2:3", SPIDERS, BUSTLE])
+        .assert()
+        .success()
+        .stdout(
+            "I keep house\ncasually.\n\
+             The morning after death\nIs solemnest of industries\n",
+        );
+    Command::cargo_bin(PRG)?
+        .args(["--range=8:", "-n", BUSTLE])
+        .assert()
+        .success()
+        .stdout("     1\tWe shall not want to use again\n     2\tUntil eternity.\n");
+    Command::cargo_bin(PRG)?
+        .args(["--range", "5:2", BUSTLE])
+        .assert()
+        .failure()
+        .stderr("invalid line range: '5:2'\n");
+    Ok(())
+}
@@ -1,74 +1,221 @@
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate, input, record,
+};
+use regex::bytes::Regex;
 use std::{
+    collections::VecDeque,
     error::Error,
-    fs::File,
-    io::{self, BufRead, BufReader, Read},
+    ffi::OsString,
+    fmt,
+    io::{self, BufRead, Read, Write},
 };
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, HeadrError>;
+
+#[derive(Debug)]
+pub enum HeadrError {
+    IllegalLineCount(String),
+    IllegalByteCount(String),
+    InvalidPattern(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for HeadrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadrError::IllegalLineCount(val) => write!(f, "illegal line count -- {}", val),
+            HeadrError::IllegalByteCount(val) => write!(f, "illegal byte count -- {}", val),
+            HeadrError::InvalidPattern(pattern) => write!(f, "Invalid pattern \"{}\"", pattern),
+            HeadrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for HeadrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HeadrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HeadrError {
+    fn from(e: io::Error) -> Self {
+        HeadrError::Io(e)
+    }
+}
+
+/// How much of each file to print
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Count {
+    /// the first N lines or bytes
+    First(usize),
+    /// everything but the last N, from `-N`
+    AllBut(usize),
+}
+
+/// Where a pattern ends the lines printed from each file
+#[derive(Debug)]
+pub enum Bound {
+    /// at the first line matching, which is printed if `inclusive`
+    Until { pattern: Regex, inclusive: bool },
+    /// at the first line not matching
+    While(Regex),
+}
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    lines: usize,
-    bytes: Option<usize>,
+    /// `None` when only a `bound` limits the lines
+    lines: Option<Count>,
+    bytes: Option<Count>,
+    bound: Option<Bound>,
+    terminator: u8,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("headr")
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILES: Opt = Opt::operand("files").multiple();
+    const LINES: Opt = Opt::option("lines").short('n').long("lines");
+    const BYTES: Opt = Opt::option("bytes")
+        .short('c')
+        .long("bytes")
+        .conflicts_with(&["lines"]);
+    const UNTIL: Opt = Opt::option("until")
+        .long("until")
+        .conflicts_with(&["bytes", "while"]);
+    const INCLUDE_MATCH: Opt = Opt::flag("include_match").long("include-match");
+    const WHILE: Opt = Opt::option("while")
+        .long("while")
+        .conflicts_with(&["bytes"]);
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("headr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust head")
+        .after_help("EXIT STATUS:\n    0 if every FILE was read, 1 if any FILE could not be opened")
         .arg(
-            Arg::with_name("files")
+            FILES
+                .arg()
                 .value_name("FILES")
                 .help("Input file(s)")
-                .multiple(true)
                 .default_value("-"),
         )
         .arg(
-            Arg::with_name("lines")
+            LINES
+                .arg()
                 .value_name("LINES")
-                .short("n")
-                .long("lines")
-                .help("Number of lines")
+                .help(
+                    "Number of lines, or with a leading '-', all but the last LINES; \
+                     may have a multiplier suffix such as K, KB or KiB",
+                )
+                .allow_hyphen_values(true)
                 .default_value("10"),
         )
         .arg(
-            Arg::with_name("bytes")
+            BYTES
+                .arg()
                 .value_name("BYTES")
-                .short("c")
-                .long("bytes")
-                .help("Number of bytes")
-                .takes_value(true)
-                .conflicts_with("lines"),
+                .help(
+                    "Number of bytes, or with a leading '-', all but the last BYTES; \
+                     may have a multiplier suffix such as K, KB or KiB",
+                )
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            UNTIL
+                .arg()
+                .value_name("REGEX")
+                .help("Print lines up to the first one matching REGEX, with -n as a limit"),
+        )
+        .arg(
+            INCLUDE_MATCH
+                .arg()
+                .help("Also print the line that matched --until")
+                .requires("until"),
         )
-        .get_matches();
+        .arg(
+            WHILE
+                .arg()
+                .value_name("REGEX")
+                .help("Print lines as long as they match REGEX, with -n as a limit"),
+        )
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [
+        FILES,
+        LINES,
+        BYTES,
+        UNTIL,
+        INCLUDE_MATCH,
+        WHILE,
+        record::OPT,
+    ];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let files = matches.values_of_lossy("files").unwrap();
 
     let lines = matches
         .value_of("lines")
-        .map(parse_positive_int)
+        .map(parse_count)
         .transpose()
-        .map_err(|e| format!("illegal line count -- {}", e))?;
+        .map_err(HeadrError::IllegalLineCount)?;
 
     let bytes = matches
         .value_of("bytes")
-        .map(parse_positive_int)
+        .map(parse_count)
         .transpose()
-        .map_err(|e| format!("illegal byte count -- {}", e))?;
+        .map_err(HeadrError::IllegalByteCount)?;
+
+    let regex = |pattern: &str| {
+        Regex::new(pattern).map_err(|_| HeadrError::InvalidPattern(pattern.to_string()))
+    };
+    let bound = if let Some(pattern) = matches.value_of("until") {
+        Some(Bound::Until {
+            pattern: regex(pattern)?,
+            inclusive: matches.is_present("include_match"),
+        })
+    } else if let Some(pattern) = matches.value_of("while") {
+        Some(Bound::While(regex(pattern)?))
+    } else {
+        None
+    };
+
+    // with a pattern, the default of ten lines no longer applies
+    let lines = match bound {
+        Some(_) if matches.occurrences_of("lines") == 0 => None,
+        _ => lines,
+    };
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         files,
-        lines: lines.unwrap(),
+        lines,
         bytes,
-    })
+        bound,
+        terminator: record::terminator(&matches),
+    }))
 }
 
-fn parse_positive_int(value: &str) -> MyResult<usize> {
-    match value.parse() {
-        Ok(n) if n > 0 => Ok(n),
+fn parse_positive_int(value: &str) -> Result<usize, String> {
+    match parse_size(value) {
+        Some(n) if n > 0 => Ok(n),
         _ => Err(value.into()),
     }
 }
@@ -91,47 +238,291 @@ fn test_parse_positive_int() {
     assert_eq!(res.unwrap_err().to_string(), "0".to_string());
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+/// The value of a size suffix: b for 512-byte blocks, KB, MB… for powers
+/// of 1000, and K, M… or KiB, MiB… for powers of 1024
+fn multiplier(suffix: &str) -> Option<u64> {
+    let (unit, base) = match suffix {
+        "" => return Some(1),
+        "b" => return Some(512),
+        _ => match suffix.as_bytes() {
+            [unit, b'B'] => (*unit, 1000),
+            [unit] | [unit, b'i', b'B'] => (*unit, 1024),
+            _ => return None,
+        },
+    };
+    let power = b"KMGTPE"
+        .iter()
+        .position(|&c| c == unit.to_ascii_uppercase())?;
+    // only the kilo prefix is also commonly written in lowercase
+    if unit.is_ascii_lowercase() && unit != b'k' {
+        return None;
+    }
+    Some((0..=power).fold(1, |value, _| value * base))
+}
+
+/// A number with an optional size suffix. Counts too large for memory
+/// saturate, since they can only mean "everything".
+fn parse_size(value: &str) -> Option<usize> {
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let number: u64 = match &value[..digits] {
+        "" => return None,
+        // more digits than fit are still a valid, huge number
+        number => number.parse().unwrap_or(u64::MAX),
+    };
+    let size = number.saturating_mul(multiplier(&value[digits..])?);
+    Some(usize::try_from(size).unwrap_or(usize::MAX))
+}
+
+/// A positive count, or "-N" (which may be zero) for all but the last N
+fn parse_count(value: &str) -> Result<Count, String> {
+    match value.strip_prefix('-') {
+        Some(num) => parse_size(num)
+            .map(Count::AllBut)
+            .ok_or_else(|| value.into()),
+        None => parse_positive_int(value).map(Count::First),
+    }
+}
+
+#[test]
+fn test_parse_count() {
+    assert_eq!(parse_count("3"), Ok(Count::First(3)));
+    assert_eq!(parse_count("-3"), Ok(Count::AllBut(3)));
+    assert_eq!(parse_count("-0"), Ok(Count::AllBut(0)));
+    assert_eq!(parse_count("0"), Err("0".to_string()));
+    assert_eq!(parse_count("0K"), Err("0K".to_string()));
+    assert_eq!(parse_count("--3"), Err("--3".to_string()));
+    assert_eq!(parse_count("-"), Err("-".to_string()));
+    assert_eq!(parse_count("K"), Err("K".to_string()));
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("2b"), Some(1024));
+    assert_eq!(parse_size("2K"), Some(2048));
+    assert_eq!(parse_size("2k"), Some(2048));
+    assert_eq!(parse_size("2KiB"), Some(2048));
+    assert_eq!(parse_size("2KB"), Some(2000));
+    assert_eq!(parse_size("2kB"), Some(2000));
+    assert_eq!(parse_size("1M"), Some(1 << 20));
+    assert_eq!(parse_size("1MiB"), Some(1 << 20));
+    assert_eq!(parse_size("3MB"), Some(3_000_000));
+    assert_eq!(parse_size("1G"), Some(1 << 30));
+    assert_eq!(parse_size("1GB"), Some(1_000_000_000));
+    assert_eq!(parse_size("99999999999999999999E"), Some(usize::MAX));
+    for bad in ["", "1m", "1KIB", "1Ki", "1X", "1.5K", "K1", "1 K"] {
+        assert_eq!(parse_size(bad), None, "{}", bad);
+    }
+}
+
+/// The records of `reader`, terminators included, as raw bytes
+fn records<R: BufRead>(mut reader: R, terminator: u8) -> impl Iterator<Item = io::Result<Vec<u8>>> {
+    std::iter::from_fn(move || {
+        let mut record = vec![];
+        match reader.read_until(terminator, &mut record) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(record)),
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// The leading `records` that `bound` lets through
+fn bounded<'a, I>(
+    records: I,
+    bound: Option<&'a Bound>,
+    terminator: u8,
+) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a
+where
+    I: Iterator<Item = io::Result<Vec<u8>>> + 'a,
+{
+    let mut done = false;
+    records.map_while(move |record| {
+        let record = match record {
+            _ if done => return None,
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        let body = record.strip_suffix(&[terminator]).unwrap_or(&record);
+        match bound {
+            Some(Bound::Until { pattern, inclusive }) if pattern.is_match(body) => {
+                done = true;
+                Some(Ok(record)).filter(|_| *inclusive)
+            }
+            Some(Bound::While(pattern)) if !pattern.is_match(body) => None,
+            _ => Some(Ok(record)),
+        }
+    })
+}
+
+/// Print `records`, or only as many as `count` says
+fn print_lines<I>(mut records: I, count: Option<Count>, out: &mut impl Write) -> io::Result<()>
+where
+    I: Iterator<Item = io::Result<Vec<u8>>>,
+{
+    match count {
+        None => records.try_for_each(|record| out.write_all(&record?)),
+        Some(Count::First(num)) => records
+            .take(num)
+            .try_for_each(|record| out.write_all(&record?)),
+        Some(Count::AllBut(num)) => all_but_lines(records, num, out),
+    }
+}
+
+/// Print all but the last `count` records, holding back at most that many
+fn all_but_lines<I>(records: I, count: usize, out: &mut impl Write) -> io::Result<()>
+where
+    I: Iterator<Item = io::Result<Vec<u8>>>,
+{
+    let mut held: VecDeque<Vec<u8>> = VecDeque::new();
+    for record in records {
+        held.push_back(record?);
+        if held.len() > count {
+            out.write_all(&held.pop_front().unwrap())?;
+        }
+    }
+    Ok(())
+}
+
+/// Print all but the last `count` bytes, holding back at most that many
+/// plus one buffer
+fn all_but_bytes(file: &mut impl Read, count: usize, out: &mut impl Write) -> io::Result<()> {
+    let mut held: VecDeque<u8> = VecDeque::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let len = match file.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        held.extend(&buf[..len]);
+        if held.len() > count {
+            // the oldest bytes, which may wrap around the end of the ring
+            let ready = held.len() - count;
+            let (front, back) = held.as_slices();
+            let split = ready.min(front.len());
+            out.write_all(&front[..split])?;
+            out.write_all(&back[..ready - split])?;
+            held.drain(..ready);
+        }
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+#[test]
+fn test_all_but_bytes_short_reads() {
+    // a reader that hands out a few bytes at a time, so the ring wraps
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(7).min(self.0.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+    let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    for count in [0, 1, 6, 7, 8, 100, 999, 1000, 2000] {
+        let mut out = vec![];
+        all_but_bytes(&mut Trickle(&data), count, &mut out).unwrap();
+        assert_eq!(
+            out,
+            &data[..data.len().saturating_sub(count)],
+            "count {}",
+            count
+        );
+    }
+}
+
+#[test]
+fn test_all_but() {
+    let text = "one\ntwo\nthree\nno newline";
+    for (count, expected) in [
+        (0, text),
+        (1, "one\ntwo\nthree\n"),
+        (3, "one\n"),
+        (4, ""),
+        (9, ""),
+    ] {
+        let mut out = vec![];
+        all_but_lines(records(text.as_bytes(), b'\n'), count, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+    for (count, expected) in [(0, text), (11, "one\ntwo\nthree"), (99, "")] {
+        let mut out = vec![];
+        all_but_bytes(&mut text.as_bytes(), count, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_bounded() {
+    let text = "From: a\nTo: b\n\nbody\nFrom: c\n";
+    let bound = |bound| {
+        let mut out = vec![];
+        let records = bounded(records(text.as_bytes(), b'\n'), Some(&bound), b'\n');
+        print_lines(records, None, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let until = |pattern, inclusive| Bound::Until {
+        pattern: Regex::new(pattern).unwrap(),
+        inclusive,
+    };
+    assert_eq!(bound(until("^$", false)), "From: a\nTo: b\n");
+    assert_eq!(bound(until("^$", true)), "From: a\nTo: b\n\n");
+    assert_eq!(bound(until("^body", false)), "From: a\nTo: b\n\n");
+    assert_eq!(bound(until("nowhere", false)), text);
+    assert_eq!(
+        bound(Bound::While(Regex::new(":").unwrap())),
+        "From: a\nTo: b\n"
+    );
+    assert_eq!(bound(Bound::While(Regex::new("^X").unwrap())), "");
+}
+
+/// Returns the exit status: 1 if any file could not be opened or read.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    let mut status = 0;
     let num_files = config.files.len();
     for (file_index, filename) in config.files.iter().enumerate() {
-        match open(&filename) {
-            Err(err) => eprintln!("head: {}: {}\n", filename, err),
+        match input::open(filename) {
+            Err(err) => {
+                eprintln!("{}", err);
+                status = 1;
+            }
             Ok(mut file) => {
                 if num_files > 1 {
-                    println!(
+                    writeln!(
+                        out,
                         "{}==> {} <==",
                         if file_index > 0 { "\n" } else { "" },
                         filename
-                    );
+                    )?;
                 }
 
-                if let Some(num_bytes) = config.bytes {
-                    let mut handle = file.take(num_bytes as u64);
-                    let mut buffer = vec![0; num_bytes];
-                    let bytes_read = handle.read(&mut buffer)?;
-                    print!("{}", String::from_utf8_lossy(&buffer[..bytes_read]));
-                } else {
-                    let mut line = String::new();
-                    for _ in 0..config.lines {
-                        let bytes = file.read_line(&mut line)?;
-                        if bytes == 0 {
-                            break;
-                        }
-
-                        print!("{}", line);
-                        line.clear();
+                let printed = match (config.bytes, config.lines) {
+                    (Some(Count::First(num_bytes)), _) => {
+                        // as raw bytes, even if that cuts a character in half
+                        io::copy(&mut file.by_ref().take(num_bytes as u64), out).map(drop)
+                    }
+                    (Some(Count::AllBut(num_bytes)), _) => all_but_bytes(&mut file, num_bytes, out),
+                    (None, lines) => {
+                        let records = records(&mut file, config.terminator);
+                        let records = bounded(records, config.bound.as_ref(), config.terminator);
+                        print_lines(records, lines, out)
                     }
+                };
+                match printed {
+                    // the error names the file; go on to the next one
+                    Err(err) if input::is_read_error(&err) => {
+                        eprintln!("{}", err);
+                        status = 1;
+                    }
+                    printed => printed?,
                 }
             }
         }
     }
 
-    Ok(())
+    Ok(status)
 }
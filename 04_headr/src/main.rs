@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = headr::get_args().and_then(headr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| headr::get_args().and_then(|parsed| parsed.run(out, headr::run)),
+        1,
+    ));
 }
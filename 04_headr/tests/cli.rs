@@ -1,11 +1,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::io::prelude::*;
-use std::{
-    error::Error,
-    fs::{self, File},
-};
+use std::{error::Error, fs};
 
 type TestResult = Result<(), Box<dyn Error>>;
 
@@ -93,17 +89,14 @@ fn skips_bad_file() -> TestResult {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
+    // -c prints raw bytes, which may cut a character in half
+    let expected = fs::read(expected_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(expected);
 
     Ok(())
 }
@@ -114,18 +107,15 @@ fn run_stdin(
     input_file: &str,
     expected_file: &str,
 ) -> TestResult {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
+    // -c prints raw bytes, which may cut a character in half
+    let expected = fs::read(expected_file)?;
     let input = fs::read_to_string(input_file)?;
 
     Command::cargo_bin(PRG)?
         .write_stdin(input)
         .args(args)
         .assert()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(expected);
 
     Ok(())
 }
@@ -419,3 +409,160 @@ fn multiple_files_c4() -> TestResult {
         "tests/expected/all.c4.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", "-z"])
+        .write_stdin("one\ntwo\0three\0four\0")
+        .assert()
+        .success()
+        .stdout("one\ntwo\0three\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_but_last() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-2", TWO, "-"])
+        .write_stdin("a\nb\nc\nd")
+        .assert()
+        .success()
+        .stdout("==> ./tests/inputs/two.txt <==\n\n==> - <==\na\nb\n");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-4"])
+        .write_stdin("header\nbody\nTRLR")
+        .assert()
+        .success()
+        .stdout("header\nbody\n");
+    Command::cargo_bin(PRG)?
+        .args(["--lines=-0", THREE])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(THREE)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_binary() -> TestResult {
+    let data: Vec<u8> = (0..=255).cycle().take(3000).collect();
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2K"])
+        .write_stdin(data.clone())
+        .assert()
+        .success()
+        .stdout(data[..2048].to_vec());
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2kB"])
+        .write_stdin(data.clone())
+        .assert()
+        .success()
+        .stdout(data[..2000].to_vec());
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-1b"])
+        .write_stdin(data.clone())
+        .assert()
+        .success()
+        .stdout(data[..3000 - 512].to_vec());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_suffix() -> TestResult {
+    let input: String = (1..=1500).map(|i| format!("{}\n", i)).collect();
+    let expected: String = (1..=1024).map(|i| format!("{}\n", i)).collect();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1K"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1Q", EMPTY])
+        .assert()
+        .failure()
+        .stderr("illegal line count -- 1Q\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn until() -> TestResult {
+    let mbox = "From: a\nSubject: hi\n\nbody\n";
+    Command::cargo_bin(PRG)?
+        .args(["--until", "^$"])
+        .write_stdin(mbox)
+        .assert()
+        .success()
+        .stdout("From: a\nSubject: hi\n");
+    Command::cargo_bin(PRG)?
+        .args(["--until", "^$", "--include-match"])
+        .write_stdin(mbox)
+        .assert()
+        .success()
+        .stdout("From: a\nSubject: hi\n\n");
+    Command::cargo_bin(PRG)?
+        .args(["--until", "^$", "-n", "1"])
+        .write_stdin(mbox)
+        .assert()
+        .success()
+        .stdout("From: a\n");
+    Command::cargo_bin(PRG)?
+        .args(["--until", "^three", TEN, "-", "-n", "4"])
+        .write_stdin(mbox)
+        .assert()
+        .success()
+        .stdout("==> ./tests/inputs/ten.txt <==\none\ntwo\n\n==> - <==\nFrom: a\nSubject: hi\n\nbody\n");
+    Command::cargo_bin(PRG)?
+        .args(["--until", "(", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid pattern \"(\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn while_matching() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--while", "^[[:alpha:]]+:"])
+        .write_stdin("From: a\nSubject: hi\n\nbody\n")
+        .assert()
+        .success()
+        .stdout("From: a\nSubject: hi\n");
+    Command::cargo_bin(PRG)?
+        .args(["--while", "o", TEN, THREE])
+        .assert()
+        .success()
+        .stdout("==> ./tests/inputs/ten.txt <==\none\ntwo\n\n==> ./tests/inputs/three.txt <==\n");
+    Command::cargo_bin(PRG)?
+        .args(["--while", "a", "--until", "b", EMPTY])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_unreadable_file() -> TestResult {
+    let truncated = "./tests/inputs/truncated.txt.gz";
+    let one = fs::read_to_string(ONE)?;
+    for (args, rest) in [
+        (vec![], one.as_str()),
+        (vec!["-c", "-3"], &one[..one.len() - 3]),
+        (vec!["-n", "-1"], ""),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .args([truncated, ONE])
+            .assert()
+            .code(1)
+            .stderr(format!("{}: incomplete deflate stream\n", truncated))
+            .stdout(format!("==> {} <==\n\n==> {} <==\n{}", truncated, ONE, rest));
+    }
+    Ok(())
+}
@@ -0,0 +1,500 @@
+//! Counting raw bytes: lines are newline bytes, characters come from UTF-8
+//! decoding in which every invalid sequence counts as one character, and
+//! words are runs of characters that are not whitespace. Runs of ASCII skip
+//! the decoding, and display widths are only worked out for `-L`. Operands
+//! are counted on worker threads, and a large regular file is split into
+//! pieces that are counted at the same time.
+
+use std::{
+    cmp,
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+#[cfg(unix)]
+use std::{fs::File, os::unix::fs::FileExt};
+
+use clr_common::input::{self, InputError, Opened};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{files::Operand, FileInfo};
+
+const BUF_SIZE: usize = 128 * 1024;
+
+/// Columns between tab stops
+const TAB_WIDTH: usize = 8;
+
+/// The smallest piece of a file worth a thread of its own
+#[cfg(unix)]
+const MIN_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// A count in progress, fed one buffer at a time
+#[derive(Default)]
+struct Counter {
+    info: FileInfo,
+    /// whether to measure the display width of lines
+    widths: bool,
+    in_word: bool,
+    /// the display column the current line has reached
+    col: usize,
+    /// the start of a character cut off at the end of the last buffer
+    partial: Vec<u8>,
+}
+
+impl Counter {
+    fn new(widths: bool) -> Counter {
+        Counter {
+            widths,
+            ..Counter::default()
+        }
+    }
+
+    fn feed(&mut self, mut data: &[u8]) {
+        self.info.num_bytes += data.len();
+        self.info.num_lines += data.iter().filter(|&&byte| byte == b'\n').count();
+
+        // finish the cut off character a byte at a time
+        while !self.partial.is_empty() {
+            let Some((&byte, rest)) = data.split_first() else {
+                return;
+            };
+            self.partial.push(byte);
+            match std::str::from_utf8(&self.partial) {
+                Ok(s) => {
+                    let c = s.chars().next().unwrap();
+                    self.partial.clear();
+                    self.char(c);
+                    data = rest;
+                }
+                Err(e) if e.error_len().is_none() => data = rest,
+                // `byte` cannot continue the character, so it is read again
+                Err(_) => {
+                    self.partial.clear();
+                    self.char(char::REPLACEMENT_CHARACTER);
+                }
+            }
+        }
+
+        // an ASCII byte is never part of a longer character, so the text
+        // splits into runs of ASCII and runs of everything else
+        while !data.is_empty() {
+            let ascii = data.iter().position(|byte| !byte.is_ascii());
+            let (run, rest) = data.split_at(ascii.unwrap_or(data.len()));
+            self.ascii(run);
+            let other = rest.iter().position(u8::is_ascii);
+            let (run, rest) = rest.split_at(other.unwrap_or(rest.len()));
+            self.decode(run, rest.is_empty());
+            data = rest;
+        }
+    }
+
+    /// Count a run of ASCII, a byte to a character.
+    fn ascii(&mut self, run: &[u8]) {
+        self.info.num_chars += run.len();
+        for &byte in run {
+            // what `char::is_whitespace` says of ASCII
+            let space = matches!(byte, b' ' | b'\t'..=b'\r');
+            if !space && !self.in_word {
+                self.info.num_words += 1;
+            }
+            self.in_word = !space;
+        }
+        if self.widths {
+            for &byte in run {
+                match byte {
+                    b'\t' => self.col += TAB_WIDTH - self.col % TAB_WIDTH,
+                    b'\n' | b'\r' | b'\x0c' => self.end_line(),
+                    b' '..=b'~' => self.col += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Decode a run without ASCII, which may stop in the middle of a
+    /// character if it is at the `end` of the buffer.
+    fn decode(&mut self, run: &[u8], end: bool) {
+        let mut chunks = run.utf8_chunks().peekable();
+        while let Some(chunk) = chunks.next() {
+            chunk.valid().chars().for_each(|c| self.char(c));
+            let invalid = chunk.invalid();
+            if invalid.is_empty() {
+                continue;
+            }
+            let cut_off = end
+                && chunks.peek().is_none()
+                && std::str::from_utf8(invalid).is_err_and(|e| e.error_len().is_none());
+            if cut_off {
+                self.partial.extend_from_slice(invalid);
+            } else {
+                self.char(char::REPLACEMENT_CHARACTER);
+            }
+        }
+    }
+
+    /// Count `c` as a terminal would show it: wide characters take two
+    /// columns, combining marks and control characters none, and tabs run
+    /// to the next tab stop. Carriage returns and form feeds start the
+    /// line over.
+    fn char(&mut self, c: char) {
+        self.info.num_chars += 1;
+        let space = c.is_whitespace();
+        if !space && !self.in_word {
+            self.info.num_words += 1;
+        }
+        self.in_word = !space;
+
+        if !self.widths {
+            return;
+        }
+        match c {
+            '\t' => self.col += TAB_WIDTH - self.col % TAB_WIDTH,
+            '\n' | '\r' | '\x0c' => self.end_line(),
+            _ => self.col += c.width().unwrap_or(0),
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.info.max_line_length = cmp::max(self.info.max_line_length, self.col);
+        self.col = 0;
+    }
+
+    fn finish(mut self) -> FileInfo {
+        if !self.partial.is_empty() {
+            self.char(char::REPLACEMENT_CHARACTER);
+        }
+        self.end_line();
+        self.info
+    }
+}
+
+/// Count everything `reader` has left, measuring the longest line only if
+/// `widths` is set.
+pub fn count(mut reader: impl Read, widths: bool) -> io::Result<FileInfo> {
+    let mut counter = Counter::new(widths);
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(counter.finish()),
+            Ok(len) => counter.feed(&buf[..len]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Where the first line at or after `from` ends, or `len`
+#[cfg(unix)]
+fn next_line(file: &File, mut from: u64, len: u64) -> io::Result<u64> {
+    let mut buf = vec![0; BUF_SIZE];
+    while from < len {
+        match file.read_at(&mut buf, from) {
+            Ok(0) => break,
+            Ok(read) => match buf[..read].iter().position(|&byte| byte == b'\n') {
+                Some(pos) => return Ok(from + pos as u64 + 1),
+                None => from += read as u64,
+            },
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+/// Count the bytes of `file` from `start` up to `end`
+#[cfg(unix)]
+fn count_range(file: &File, mut start: u64, end: u64, widths: bool) -> io::Result<FileInfo> {
+    let mut counter = Counter::new(widths);
+    let mut buf = vec![0; BUF_SIZE];
+    while start < end {
+        let want = cmp::min(BUF_SIZE as u64, end - start) as usize;
+        match file.read_at(&mut buf[..want], start) {
+            Ok(0) => break,
+            Ok(read) => {
+                counter.feed(&buf[..read]);
+                start += read as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(counter.finish())
+}
+
+/// Count the first `len` bytes of `file` in `chunks` pieces, each on a
+/// thread of its own. The pieces end right after a newline, where a count
+/// starts afresh, so their counts simply add up.
+#[cfg(unix)]
+fn count_chunked(file: &File, len: u64, chunks: usize, widths: bool) -> io::Result<FileInfo> {
+    let mut bounds = vec![0];
+    for i in 1..chunks as u64 {
+        let from = cmp::max(len * i / chunks as u64, *bounds.last().unwrap());
+        bounds.push(next_line(file, from, len)?);
+    }
+    bounds.push(len);
+    bounds.dedup();
+
+    thread::scope(|scope| {
+        let pieces: Vec<_> = bounds
+            .windows(2)
+            .map(|piece| scope.spawn(move || count_range(file, piece[0], piece[1], widths)))
+            .collect();
+        let mut info = FileInfo::default();
+        for piece in pieces {
+            info.add(
+                &piece
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))?,
+            );
+        }
+        Ok(info)
+    })
+}
+
+fn named(filename: &str, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), InputError::new(filename, e))
+}
+
+/// Count one operand, splitting a large regular file among `jobs`
+/// threads. Errors name the operand.
+pub fn count_file(filename: &str, jobs: usize, widths: bool) -> io::Result<FileInfo> {
+    let opened = input::open_raw(filename).map_err(|e| io::Error::new(e.kind(), e))?;
+    match opened {
+        #[cfg(unix)]
+        Opened::Plain(head, input::Source::File(file)) => {
+            let metadata = file.metadata().map_err(|e| named(filename, e))?;
+            let chunks = match metadata.is_file() {
+                true => cmp::min(jobs as u64, metadata.len() / MIN_CHUNK_SIZE) as usize,
+                false => 0,
+            };
+            if chunks > 1 {
+                count_chunked(&file, metadata.len(), chunks, widths)
+            } else {
+                count(head.as_slice().chain(file), widths)
+            }
+            .map_err(|e| named(filename, e))
+        }
+        Opened::Plain(head, source) => {
+            count(head.as_slice().chain(source), widths).map_err(|e| named(filename, e))
+        }
+        // `Input` names the file in its own errors
+        Opened::Decoded(input) => count(input, widths),
+    }
+}
+
+/// Count `operands` on up to `jobs` threads and pass each result to
+/// `report` in order. stdin is counted in its turn on the calling thread,
+/// so that repeating "-" reads it once, as it would one file at a time.
+pub fn count_files<F>(
+    operands: Vec<Operand>,
+    jobs: usize,
+    widths: bool,
+    mut report: F,
+) -> io::Result<()>
+where
+    F: FnMut(&str, io::Result<FileInfo>) -> io::Result<()>,
+{
+    // operands that could not be found are done with already
+    let mut files = Vec::with_capacity(operands.len());
+    let mut done: Vec<Option<io::Result<FileInfo>>> = Vec::with_capacity(operands.len());
+    for operand in operands {
+        match operand {
+            Ok(filename) => {
+                files.push(filename);
+                done.push(None);
+            }
+            Err(e) => {
+                files.push(e.filename().to_string());
+                done.push(Some(Err(io::Error::new(e.kind(), e))));
+            }
+        }
+    }
+    let todo: Vec<bool> = done.iter().map(Option::is_none).collect();
+    // a lone file gets every thread to itself
+    let (workers, jobs) = match files.len() {
+        1 => (0, jobs),
+        len => (cmp::min(jobs, len), 1),
+    };
+
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        // the receiver goes when this closure returns, before the scope
+        // waits for the workers, so a failed report stops them too
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (files, todo, next) = (&files, &todo, &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                match files.get(i) {
+                    None => break,
+                    Some(filename) if filename == "-" || !todo[i] => {}
+                    Some(filename) => {
+                        if tx.send((i, count_file(filename, jobs, widths))).is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for (i, filename) in files.iter().enumerate() {
+            let result = if todo[i] && (filename == "-" || workers == 0) {
+                count_file(filename, jobs, widths)
+            } else {
+                loop {
+                    if let Some(result) = done[i].take() {
+                        break result;
+                    }
+                    let (j, result) = rx.recv().expect("a counting thread died");
+                    done[j] = Some(result);
+                }
+            };
+            report(filename, result)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count, count_files, Counter};
+    use crate::FileInfo;
+
+    fn count_str(text: &[u8]) -> FileInfo {
+        count(text, true).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // a Latin-1 "é", a truncated "€", a stray continuation byte
+        let info = count_str(b"caf\xe9 au lait\n\xe2\x82 x \x80\n");
+        assert_eq!(info.num_lines, 2);
+        assert_eq!(info.num_words, 6);
+        assert_eq!(info.num_bytes, 20);
+        assert_eq!(info.num_chars, 19);
+        assert_eq!(info.max_line_length, 12);
+        // cut off at the very end
+        assert_eq!(count_str(b"ab\xe2\x82").num_chars, 3);
+    }
+
+    #[test]
+    fn test_split_buffers() {
+        let text = [
+            "naïve 日本語\tcafe\u{301}\n\u{1f600} ".as_bytes(),
+            b"\xe2\x82x\n",
+        ]
+        .concat();
+        let whole = count_str(&text);
+        for size in 1..=5 {
+            let mut counter = Counter::new(true);
+            text.chunks(size).for_each(|piece| counter.feed(piece));
+            assert_eq!(counter.finish(), whole, "buffers of {}", size);
+        }
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        for (text, width) in [
+            ("", 0),
+            ("hello\n", 5),
+            ("a\tb", 9),
+            ("\t\t", 16),
+            ("1234567\tx", 9),
+            ("日本語", 6),
+            ("e\u{301}te\u{301}", 3),
+            ("long line\rshort", 9),
+            ("ab\x0cabcd\n", 4),
+        ] {
+            assert_eq!(
+                count_str(text.as_bytes()).max_line_length,
+                width,
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_without_widths() {
+        for text in [
+            "plain ascii\x0bwith\x0cfeeds\r\n".as_bytes(),
+            "naïve 日本語\tcafe\u{301}\n\u{a0}x".as_bytes(),
+            b"caf\xe9 au lait\n\xe2\x82 x \x80\n",
+        ] {
+            let info = count(text, false).unwrap();
+            assert_eq!(info.max_line_length, 0);
+            assert_eq!(
+                info,
+                FileInfo {
+                    max_line_length: 0,
+                    ..count_str(text)
+                }
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_count_chunked() {
+        use std::io::Write;
+
+        let text: String = (0..2000)
+            .map(|i| {
+                format!(
+                    "{} línea {}\t{}\n",
+                    i,
+                    "日".repeat(i % 7),
+                    "x".repeat(i % 90)
+                )
+            })
+            .collect();
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+        let whole = count_str(text.as_bytes());
+        for chunks in [2, 3, 8, 5000] {
+            let len = text.len() as u64;
+            assert_eq!(
+                super::count_chunked(&file, len, chunks, true).unwrap(),
+                whole
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_files_in_order() {
+        let files: Vec<String> = [
+            "tests/inputs/fox.txt",
+            "nonexistent",
+            "tests/inputs/empty.txt",
+        ]
+        .iter()
+        .cycle()
+        .take(30)
+        .map(|s| s.to_string())
+        .collect();
+        let mut seen = vec![];
+        let operands = files.iter().cloned().map(Ok).collect();
+        count_files(operands, 4, false, |filename, result| {
+            seen.push((filename.to_string(), result.map(|info| info.num_bytes).ok()));
+            Ok(())
+        })
+        .unwrap();
+        let expected: Vec<_> = files
+            .iter()
+            .map(|filename| {
+                let bytes = match filename.as_str() {
+                    "tests/inputs/fox.txt" => Some(48),
+                    "tests/inputs/empty.txt" => Some(0),
+                    _ => None,
+                };
+                (filename.clone(), bytes)
+            })
+            .collect();
+        assert_eq!(seen, expected);
+    }
+}
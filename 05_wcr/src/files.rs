@@ -0,0 +1,135 @@
+//! What to count: the operands, or the names listed in `--files0-from`,
+//! with directories walked for their files under `-r`.
+
+use std::io::{self, Read};
+
+use clr_common::input::{self, InputError};
+use walkdir::WalkDir;
+
+use globset::GlobSet;
+
+use crate::Config;
+
+/// A file to count, or why it cannot be
+pub type Operand = Result<String, InputError>;
+
+/// The names in `from`, which are separated, or ended, by NULs
+fn read_files0(from: &str) -> io::Result<Vec<Operand>> {
+    let mut list = vec![];
+    input::open(from)
+        .map_err(|e| io::Error::new(e.kind(), e))?
+        .read_to_end(&mut list)?;
+    if list.last() == Some(&b'\0') {
+        list.pop();
+    }
+    if list.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(list
+        .split(|&byte| byte == b'\0')
+        .enumerate()
+        .map(|(i, name)| match name {
+            [] => Err(InputError::new(
+                &format!("{}:{}", from, i + 1),
+                io::Error::new(io::ErrorKind::InvalidInput, "invalid zero-length file name"),
+            )),
+            _ => Ok(String::from_utf8_lossy(name).into_owned()),
+        })
+        .collect())
+}
+
+fn selected(name: &str, include: &GlobSet, exclude: &GlobSet) -> bool {
+    (include.is_empty() || include.is_match(name)) && !exclude.is_match(name)
+}
+
+/// The files under `root` in name order, or `root` itself if it is not a
+/// directory
+fn walk(root: &str, include: &GlobSet, exclude: &GlobSet) -> Vec<Operand> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| match entry {
+            // an operand is counted whatever its name
+            Ok(entry) if entry.depth() == 0 && !entry.file_type().is_dir() => {
+                Some(Ok(root.to_string()))
+            }
+            Ok(entry) => {
+                let name = entry.file_name().to_string_lossy();
+                (entry.file_type().is_file() && selected(&name, include, exclude))
+                    .then(|| Ok(entry.path().display().to_string()))
+            }
+            Err(e) => {
+                let path = e
+                    .path()
+                    .map_or_else(|| root.to_string(), |path| path.display().to_string());
+                let err = match e.io_error() {
+                    Some(_) => e.into_io_error().unwrap(),
+                    None => io::Error::other(e),
+                };
+                Some(Err(InputError::new(&path, err)))
+            }
+        })
+        .collect()
+}
+
+/// Everything `config` asks to count, in order. Only a list that cannot be
+/// read is an error; operands that cannot be found are reported in turn.
+pub fn operands(config: &Config) -> io::Result<Vec<Operand>> {
+    let names = match &config.files0_from {
+        Some(from) => read_files0(from)?,
+        None => config.files.iter().cloned().map(Ok).collect(),
+    };
+    if !config.recursive {
+        return Ok(names);
+    }
+    Ok(names
+        .into_iter()
+        .flat_map(|name| match name {
+            Ok(name) if name != "-" => walk(&name, &config.include, &config.exclude),
+            name => vec![name],
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_files0, selected};
+    use globset::{Glob, GlobSet, GlobSetBuilder};
+    use std::io::Write;
+
+    fn globs(patterns: &[&str]) -> GlobSet {
+        let mut set = GlobSetBuilder::new();
+        for pattern in patterns {
+            set.add(Glob::new(pattern).unwrap());
+        }
+        set.build().unwrap()
+    }
+
+    #[test]
+    fn test_read_files0() {
+        let mut list = tempfile::NamedTempFile::new().unwrap();
+        list.write_all(b"a.txt\0b c\0\0d").unwrap();
+        let from = list.path().to_str().unwrap();
+        let names = read_files0(from).unwrap();
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[0].as_ref().unwrap(), "a.txt");
+        assert_eq!(names[1].as_ref().unwrap(), "b c");
+        assert_eq!(
+            names[2].as_ref().unwrap_err().to_string(),
+            format!("{}:3: invalid zero-length file name", from)
+        );
+        assert_eq!(names[3].as_ref().unwrap(), "d");
+        assert!(read_files0("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_selected() {
+        let include = globs(&["*.rs", "*.toml"]);
+        let exclude = globs(&["test*"]);
+        assert!(selected("lib.rs", &include, &exclude));
+        assert!(selected("Cargo.toml", &include, &GlobSet::empty()));
+        assert!(!selected("tests.rs", &include, &exclude));
+        assert!(!selected("README.md", &include, &exclude));
+        assert!(selected("README.md", &GlobSet::empty(), &exclude));
+    }
+}
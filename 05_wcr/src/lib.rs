@@ -1,11 +1,56 @@
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+};
 use std::{
+    cmp,
     error::Error,
-    fs::File,
-    io::{stdin, BufRead, BufReader},
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
+    thread,
 };
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+mod counter;
+mod files;
+mod report;
+
+pub use counter::count;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use report::{Format, Report};
+
+type MyResult<T> = Result<T, WcrError>;
+
+#[derive(Debug)]
+pub enum WcrError {
+    InvalidPattern(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for WcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WcrError::InvalidPattern(pattern) => write!(f, "Invalid pattern \"{}\"", pattern),
+            WcrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for WcrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WcrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WcrError {
+    fn from(e: io::Error) -> Self {
+        WcrError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
@@ -14,193 +59,251 @@ pub struct Config {
     words: bool,
     bytes: bool,
     chars: bool,
+    max_line_length: bool,
+    format: Format,
+    files0_from: Option<String>,
+    recursive: bool,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("wcr")
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILES: Opt = Opt::operand("files").multiple();
+    const LINES: Opt = Opt::flag("lines").short('l').long("lines");
+    const BYTES: Opt = Opt::flag("bytes").short('c').long("bytes");
+    const WORDS: Opt = Opt::flag("words").short('w').long("words");
+    const CHARS: Opt = Opt::flag("chars")
+        .short('m')
+        .long("chars")
+        .conflicts_with(&["bytes"]);
+    const MAX_LINE_LENGTH: Opt = Opt::flag("max_line_length")
+        .short('L')
+        .long("max-line-length");
+    const FORMAT: Opt = Opt::option("format").long("format");
+    const FILES0_FROM: Opt = Opt::option("files0_from")
+        .long("files0-from")
+        .conflicts_with(&["files"]);
+    const RECURSIVE: Opt = Opt::flag("recursive").short('r').long("recursive");
+    const INCLUDE: Opt = Opt::option("include").long("include").multiple();
+    const EXCLUDE: Opt = Opt::option("exclude").long("exclude").multiple();
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("wcr")
         .version("0.1.0")
         .author("Denilson Bro")
         .about("Rust wc")
+        .after_help("EXIT STATUS:\n    0 if every FILE was read, 1 if any FILE could not be opened")
         .arg(
-            Arg::with_name("files")
+            FILES
+                .arg()
                 .value_name("FILES")
                 .help("Input file(s)")
-                .multiple(true)
                 .default_value("-"),
         )
+        .arg(LINES.arg().help("Count lines"))
+        .arg(BYTES.arg().help("Count bytes"))
+        .arg(WORDS.arg().help("Count words"))
+        .arg(CHARS.arg().help("Count chars"))
+        .arg(
+            MAX_LINE_LENGTH
+                .arg()
+                .help("Print the display width of the longest line"),
+        )
+        .arg(
+            FORMAT
+                .arg()
+                .value_name("FORMAT")
+                .help("Print counts as wc does, or as records with the totals and errors")
+                .possible_values(Format::NAMES)
+                .default_value("text"),
+        )
         .arg(
-            Arg::with_name("lines")
-                .value_name("LINES")
-                .short("l")
-                .long("lines")
-                .help("Count lines")
-                .takes_value(false),
+            FILES0_FROM
+                .arg()
+                .value_name("F")
+                .help("Read the files to count from F, separated by NULs; - is stdin"),
         )
         .arg(
-            Arg::with_name("bytes")
-                .value_name("BYTES")
-                .short("c")
-                .long("bytes")
-                .help("Count bytes")
-                .takes_value(false),
+            RECURSIVE
+                .arg()
+                .help("Count the files in directories, and in theirs"),
         )
         .arg(
-            Arg::with_name("words")
-                .value_name("WORDS")
-                .short("w")
-                .long("words")
-                .help("Count words")
-                .takes_value(false),
+            INCLUDE
+                .arg()
+                .value_name("GLOB")
+                .help("With -r, count only files whose names match GLOB")
+                .number_of_values(1)
+                .requires("recursive"),
         )
         .arg(
-            Arg::with_name("chars")
-                .value_name("CHARS")
-                .short("m")
-                .long("chars")
-                .help("Count chars")
-                .takes_value(false)
-                .conflicts_with("bytes"),
+            EXCLUDE
+                .arg()
+                .value_name("GLOB")
+                .help("With -r, skip files whose names match GLOB")
+                .number_of_values(1)
+                .requires("recursive"),
         )
-        .get_matches();
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [
+        FILES,
+        LINES,
+        BYTES,
+        WORDS,
+        CHARS,
+        MAX_LINE_LENGTH,
+        FORMAT,
+        FILES0_FROM,
+        RECURSIVE,
+        INCLUDE,
+        EXCLUDE,
+    ];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let mut lines = matches.is_present("lines");
     let mut words = matches.is_present("words");
     let mut bytes = matches.is_present("bytes");
     let chars = matches.is_present("chars");
+    let max_line_length = matches.is_present("max_line_length");
 
     // if [lines, words, bytes, chars].iter().all(|v| v == &false) {
-    if [lines, words, bytes, chars].iter().all(|v| !v) {
+    if [lines, words, bytes, chars, max_line_length]
+        .iter()
+        .all(|v| !v)
+    {
         lines = true;
         words = true;
         bytes = true;
     }
 
-    Ok(Config {
+    let globs = |name| -> MyResult<GlobSet> {
+        let invalid =
+            |e: globset::Error| WcrError::InvalidPattern(e.glob().unwrap_or_default().to_string());
+        let mut set = GlobSetBuilder::new();
+        for pattern in matches.values_of(name).into_iter().flatten() {
+            set.add(Glob::new(pattern).map_err(invalid)?);
+        }
+        set.build().map_err(invalid)
+    };
+
+    Ok(Parsed::Run(Config {
         files: matches.values_of_lossy("files").unwrap(),
         lines,
         words,
         bytes,
         chars,
-    })
+        max_line_length,
+        format: Format::parse(matches.value_of("format").unwrap()),
+        files0_from: matches.value_of("files0_from").map(String::from),
+        recursive: matches.is_present("recursive"),
+        include: globs("include")?,
+        exclude: globs("exclude")?,
+    }))
 }
 
-fn format_field(value: usize, show: bool) -> String {
-    if show {
-        format!("{:>8}", value)
-    } else {
-        "".to_string()
-    }
-}
+/// Returns the exit status: 1 if any file could not be read.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    let mut status = 0;
+    let mut total = FileInfo::default();
+    let jobs = thread::available_parallelism().map_or(1, |n| n.get());
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut total_lines = 0;
-    let mut total_words = 0;
-    let mut total_bytes = 0;
-    let mut total_chars = 0;
-
-    for filename in &config.files {
-        match open(&filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                let fileinfo = count(file).unwrap();
-                println!(
-                    "{}{}{}{}{}",
-                    format_field(fileinfo.num_lines, config.lines),
-                    format_field(fileinfo.num_words, config.words),
-                    format_field(fileinfo.num_chars, config.chars),
-                    format_field(fileinfo.num_bytes, config.bytes),
-                    if filename == "-" {
-                        "".to_string()
-                    } else {
-                        format!(" {}", filename)
-                    }
-                );
-                total_lines += fileinfo.num_lines;
-                total_words += fileinfo.num_words;
-                total_bytes += fileinfo.num_bytes;
-                total_chars += fileinfo.num_chars;
+    let operands = files::operands(&config)?;
+    let num_operands = operands.len();
+    let mut report = Report::new(&config, out)?;
+    counter::count_files(
+        operands,
+        jobs,
+        config.max_line_length,
+        |filename, result| match result {
+            Err(err) => {
+                status = 1;
+                report.error(filename, &err)
             }
-        }
-    }
-    if config.files.len() > 1 {
-        println!(
-            "{}{}{}{} total",
-            format_field(total_lines, config.lines),
-            format_field(total_words, config.words),
-            format_field(total_chars, config.chars),
-            format_field(total_bytes, config.bytes),
-        )
-    }
-
-    Ok(())
-}
+            Ok(info) => {
+                total.add(&info);
+                report.file(filename, &info)
+            }
+        },
+    )?;
+    report.total(&total, num_operands)?;
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
+    Ok(status)
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct FileInfo {
     num_lines: usize,
     num_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    max_line_length: usize,
 }
 
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut num_lines = 0;
-    let mut num_words = 0;
-    let mut num_bytes = 0;
-    let mut num_chars = 0;
-
-    let mut buf = String::new();
-    while let Ok(read_bytes) = file.read_line(&mut buf) {
-        if read_bytes == 0 {
-            break;
-        }
-        num_bytes += read_bytes;
-        num_lines += 1;
-        num_words += buf.split_whitespace().count();
-        num_chars += buf.chars().count();
-        buf.clear()
+impl FileInfo {
+    /// Fold in the counts of another file, or another piece of this one
+    fn add(&mut self, other: &FileInfo) {
+        self.num_lines += other.num_lines;
+        self.num_words += other.num_words;
+        self.num_bytes += other.num_bytes;
+        self.num_chars += other.num_chars;
+        self.max_line_length = cmp::max(self.max_line_length, other.max_line_length);
     }
-
-    Ok(FileInfo {
-        num_lines,
-        num_words,
-        num_bytes,
-        num_chars,
-    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::format_field;
-
-    use super::{count, FileInfo};
+    use super::{count, run, Config, FileInfo, Format, GlobSet};
     use std::io::Cursor;
 
     #[test]
     fn test_count() {
         let text = "I don't want the world. I just want your half.\r\n";
-        let info = count(Cursor::new(text));
+        let info = count(Cursor::new(text), true);
         assert!(info.is_ok());
         let expected = FileInfo {
             num_lines: 1,
             num_words: 10,
             num_bytes: 48,
             num_chars: 48,
+            max_line_length: 46,
         };
         assert_eq!(info.unwrap(), expected);
     }
 
     #[test]
-    fn test_format_field() {
-        assert_eq!(format_field(1, false), "");
-        assert_eq!(format_field(3, true), "       3");
-        assert_eq!(format_field(13, true), "      13");
+    fn test_run_into_vec() {
+        let config = Config {
+            files: vec!["tests/inputs/fox.txt".to_string()],
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: false,
+            max_line_length: false,
+            format: Format::Text,
+            files0_from: None,
+            recursive: false,
+            include: GlobSet::empty(),
+            exclude: GlobSet::empty(),
+        };
+        let mut out = vec![];
+        assert_eq!(run(config, &mut out).unwrap(), 0);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "       1       9      48 tests/inputs/fox.txt\n"
+        );
     }
 }
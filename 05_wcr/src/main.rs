@@ -1,8 +1,8 @@
-use wcr;
+use clr_common::output;
 
 fn main() {
-    if let Err(e) = wcr::get_args().and_then(wcr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| wcr::get_args().and_then(|parsed| parsed.run(out, wcr::run)),
+        1,
+    ));
 }
@@ -0,0 +1,247 @@
+//! Writing counts: wc's fixed-width columns, or `--format` records that a
+//! program can read, with the totals and any errors as records of their own.
+
+use std::{
+    error::Error,
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use clr_common::input::InputError;
+use csv::WriterBuilder;
+
+use crate::{Config, FileInfo};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    /// `{:>8}` columns, as wc prints them
+    Text,
+    /// one JSON object per line
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] = &["text", "json", "csv", "tsv"];
+
+    pub fn parse(name: &str) -> Format {
+        match name {
+            "json" => Format::Json,
+            "csv" => Format::Csv,
+            "tsv" => Format::Tsv,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// The counters `config` selects, named, in the order wc prints them
+fn fields(config: &Config, info: &FileInfo) -> Vec<(&'static str, usize)> {
+    [
+        ("lines", config.lines, info.num_lines),
+        ("words", config.words, info.num_words),
+        ("chars", config.chars, info.num_chars),
+        ("bytes", config.bytes, info.num_bytes),
+        (
+            "max_line_length",
+            config.max_line_length,
+            info.max_line_length,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, show, _)| *show)
+    .map(|(name, _, value)| (name, value))
+    .collect()
+}
+
+fn format_field(value: usize, show: bool) -> String {
+    if show {
+        format!("{:>8}", value)
+    } else {
+        "".to_string()
+    }
+}
+
+/// What went wrong with an operand, without its name in front
+fn message(err: &io::Error) -> String {
+    match err.get_ref().and_then(|e| e.downcast_ref::<InputError>()) {
+        Some(e) => e.source().map_or_else(|| e.to_string(), |e| e.to_string()),
+        None => err.to_string(),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c < ' ' => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+enum Sink<'a, W: Write> {
+    Text(&'a mut W),
+    Json(&'a mut W),
+    Delimited(Box<csv::Writer<&'a mut W>>),
+}
+
+/// Writes a row for each operand and the totals, in `config`'s format
+pub struct Report<'a, W: Write> {
+    config: &'a Config,
+    sink: Sink<'a, W>,
+}
+
+impl<'a, W: Write> Report<'a, W> {
+    pub fn new(config: &'a Config, out: &'a mut W) -> io::Result<Self> {
+        let sink = match config.format {
+            Format::Text => Sink::Text(out),
+            Format::Json => Sink::Json(out),
+            Format::Csv | Format::Tsv => {
+                let delimiter = if config.format == Format::Csv {
+                    b','
+                } else {
+                    b'\t'
+                };
+                let mut writer = WriterBuilder::new().delimiter(delimiter).from_writer(out);
+                let names = fields(config, &FileInfo::default());
+                let header = ["type", "file"]
+                    .into_iter()
+                    .chain(names.iter().map(|(name, _)| *name))
+                    .chain(["error"]);
+                writer.write_record(header)?;
+                Sink::Delimited(Box::new(writer))
+            }
+        };
+        Ok(Report { config, sink })
+    }
+
+    fn record(&mut self, kind: &str, filename: &str, info: &FileInfo) -> io::Result<()> {
+        let fields = fields(self.config, info);
+        match &mut self.sink {
+            Sink::Text(out) => {
+                let label = match (kind, filename) {
+                    ("total", _) => " total".to_string(),
+                    (_, "-") => "".to_string(),
+                    _ => format!(" {}", filename),
+                };
+                write_row(out, self.config, info, &label)
+            }
+            Sink::Json(out) => {
+                let mut line = format!("{{\"type\":\"{}\"", kind);
+                if kind != "total" {
+                    write!(line, ",\"file\":{}", json_string(filename)).unwrap();
+                }
+                for (name, value) in fields {
+                    write!(line, ",\"{}\":{}", name, value).unwrap();
+                }
+                writeln!(out, "{}}}", line)
+            }
+            Sink::Delimited(writer) => {
+                let values = fields.iter().map(|(_, value)| value.to_string());
+                let record = [kind.to_string(), filename.to_string()]
+                    .into_iter()
+                    .chain(values)
+                    .chain(["".to_string()]);
+                Ok(writer.write_record(record)?)
+            }
+        }
+    }
+
+    /// The counts of one operand
+    pub fn file(&mut self, filename: &str, info: &FileInfo) -> io::Result<()> {
+        self.record("file", filename, info)
+    }
+
+    /// An operand that could not be counted
+    pub fn error(&mut self, filename: &str, err: &io::Error) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Text(_) => {
+                eprintln!("{}", err);
+                Ok(())
+            }
+            Sink::Json(out) => writeln!(
+                out,
+                "{{\"type\":\"error\",\"file\":{},\"error\":{}}}",
+                json_string(filename),
+                json_string(&message(err))
+            ),
+            Sink::Delimited(writer) => {
+                let blanks = fields(self.config, &FileInfo::default()).len();
+                let message = message(err);
+                let record = ["error", filename]
+                    .into_iter()
+                    .chain(std::iter::repeat_n("", blanks))
+                    .chain([message.as_str()]);
+                Ok(writer.write_record(record)?)
+            }
+        }
+    }
+
+    /// The totals of `count` operands; plain wc shows them only for more
+    /// than one.
+    pub fn total(mut self, info: &FileInfo, count: usize) -> io::Result<()> {
+        if count > 1 || !matches!(self.sink, Sink::Text(_)) {
+            self.record("total", "", info)?;
+        }
+        match self.sink {
+            Sink::Delimited(mut writer) => writer.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn write_row(
+    out: &mut impl Write,
+    config: &Config,
+    info: &FileInfo,
+    label: &str,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}{}{}{}{}{}",
+        format_field(info.num_lines, config.lines),
+        format_field(info.num_words, config.words),
+        format_field(info.num_chars, config.chars),
+        format_field(info.num_bytes, config.bytes),
+        format_field(info.max_line_length, config.max_line_length),
+        label
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_field, json_string, message};
+    use clr_common::input::InputError;
+    use std::io;
+
+    #[test]
+    fn test_format_field() {
+        assert_eq!(format_field(1, false), "");
+        assert_eq!(format_field(3, true), "       3");
+        assert_eq!(format_field(13, true), "      13");
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("fox.txt"), "\"fox.txt\"");
+        assert_eq!(json_string("a \"b\"\\c"), "\"a \\\"b\\\"\\\\c\"");
+        assert_eq!(json_string("tab\there\n\x01"), "\"tab\\there\\n\\u0001\"");
+        assert_eq!(json_string("日本"), "\"日本\"");
+    }
+
+    #[test]
+    fn test_message() {
+        let err = InputError::new("x.txt", io::Error::other("gone"));
+        assert_eq!(message(&io::Error::other(err)), "gone");
+        assert_eq!(message(&io::Error::other("plain")), "plain");
+    }
+}
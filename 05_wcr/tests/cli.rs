@@ -9,6 +9,7 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const ATLAMAL_GZ: &str = "tests/inputs/atlamal.txt.gz";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -57,7 +58,7 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
@@ -158,6 +159,12 @@ fn atlamal_bytes_lines() -> TestResult {
     run(&["-l", "-c", ATLAMAL], "tests/expected/atlamal.txt.cl.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn atlamal_gz() -> TestResult {
+    run(&[ATLAMAL_GZ], "tests/expected/atlamal.txt.gz.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn atlamal_stdin() -> TestResult {
@@ -212,3 +219,222 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn fox_max_line_length() -> TestResult {
+    run(&["-L", FOX], "tests/expected/fox.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn atlamal_max_line_length() -> TestResult {
+    run(
+        &["--max-line-length", ATLAMAL],
+        "tests/expected/atlamal.txt.L.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_line_length() -> TestResult {
+    run(&["-L", EMPTY, FOX, ATLAMAL], "tests/expected/all.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_lines_max_line_length() -> TestResult {
+    run(&["-lL", EMPTY, FOX, ATLAMAL], "tests/expected/all.lL.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn max_line_length_display_width() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("-L")
+        .write_stdin("日本語\tx\ncafe\u{301}\n")
+        .assert()
+        .success()
+        .stdout("       9\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("-lwmL")
+        .write_stdin(&b"caf\xe9 au lait\n\xe2\x82 x \x80\nend"[..])
+        .assert()
+        .success()
+        .stdout("       2       7      22      12\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn many_files_in_order() -> TestResult {
+    let files: Vec<&str> = [FOX, EMPTY, ATLAMAL].repeat(10);
+    let mut expected = String::new();
+    for file in &files {
+        let name = file.trim_start_matches("tests/inputs/");
+        let row = fs::read_to_string(format!("tests/expected/{}.out", name))?;
+        expected.push_str(&row);
+    }
+    expected.push_str("      50     380    2250 total\n");
+    Command::cargo_bin(PRG)?
+        .args(&files)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unreadable_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("tests/inputs: "))
+        .stdout(format!(
+            "{}       1       9      48 total\n",
+            fs::read_to_string("tests/expected/fox.txt.out")?
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_json() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--format", "json", FOX, "nonexistent", "-"])
+        .write_stdin("one \"two\"\n")
+        .assert()
+        .failure()
+        .stderr("")
+        .stdout(concat!(
+            r#"{"type":"file","file":"tests/inputs/fox.txt","lines":1,"words":9,"bytes":48}"#,
+            "\n",
+            r#"{"type":"error","file":"nonexistent","error":"No such file or directory (os error 2)"}"#,
+            "\n",
+            r#"{"type":"file","file":"-","lines":1,"words":2,"bytes":10}"#,
+            "\n",
+            r#"{"type":"total","lines":2,"words":11,"bytes":58}"#,
+            "\n",
+        ));
+    Command::cargo_bin(PRG)?
+        .args(["--format=json", "-mL", FOX])
+        .assert()
+        .success()
+        .stdout(concat!(
+            r#"{"type":"file","file":"tests/inputs/fox.txt","chars":48,"max_line_length":50}"#,
+            "\n",
+            r#"{"type":"total","chars":48,"max_line_length":50}"#,
+            "\n",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_csv() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--format", "csv", "-l", FOX, "nonexistent", ATLAMAL])
+        .assert()
+        .failure()
+        .stderr("")
+        .stdout(
+            "type,file,lines,error\n\
+             file,tests/inputs/fox.txt,1,\n\
+             error,nonexistent,,No such file or directory (os error 2)\n\
+             file,tests/inputs/atlamal.txt,4,\n\
+             total,,5,\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_tsv() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--format", "tsv", EMPTY])
+        .assert()
+        .success()
+        .stdout(
+            "type\tfile\tlines\twords\tbytes\terror\n\
+             file\ttests/inputs/empty.txt\t0\t0\t0\t\n\
+             total\t\t0\t0\t0\t\n",
+        );
+    Command::cargo_bin(PRG)?
+        .args(["--format", "xml", EMPTY])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "-"])
+        .write_stdin(format!("{}\0{}\0\0nonexistent\0", FOX, ATLAMAL))
+        .assert()
+        .failure()
+        .stderr(
+            "-:3: invalid zero-length file name\n\
+             nonexistent: No such file or directory (os error 2)\n",
+        )
+        .stdout(
+            "       1       9      48 tests/inputs/fox.txt\n\
+             \x20      4      29     177 tests/inputs/atlamal.txt\n\
+             \x20      5      38     225 total\n",
+        );
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "nonexistent"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("nonexistent: "));
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "-", FOX])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-r", "tests/inputs", FOX])
+        .assert()
+        .success()
+        .stdout(
+            "       4      29     177 tests/inputs/atlamal.txt\n\
+             \x20      4      29     177 tests/inputs/atlamal.txt.gz\n\
+             \x20      0       0       0 tests/inputs/empty.txt\n\
+             \x20      1       9      48 tests/inputs/fox.txt\n\
+             \x20      1       9      48 tests/inputs/fox.txt\n\
+             \x20     10      76     450 total\n",
+        );
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-r", "--include", "*.txt", "--exclude", "e*", "tests"])
+        .assert()
+        .success()
+        .stdout(
+            "       4 tests/inputs/atlamal.txt\n\
+             \x20      1 tests/inputs/fox.txt\n\
+             \x20      5 total\n",
+        );
+    Command::cargo_bin(PRG)?
+        .args(["--include", "*.txt", "tests"])
+        .assert()
+        .failure();
+    Command::cargo_bin(PRG)?
+        .args(["-r", "--include", "[", "tests"])
+        .assert()
+        .failure()
+        .stderr("Invalid pattern \"[\"\n");
+    Ok(())
+}
@@ -1,43 +1,104 @@
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+    input::{self, InputError},
+    record,
+};
 use std::{
     error::Error,
+    ffi::OsString,
+    fmt,
     fs::File,
-    io::{stdin, stdout, BufRead, BufReader, Write},
+    io::{self, BufWriter, Write},
 };
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, UniqrError>;
+
+#[derive(Debug)]
+pub enum UniqrError {
+    Input(InputError),
+    OutFile(String, io::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for UniqrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UniqrError::Input(e) => write!(f, "{}", e),
+            UniqrError::OutFile(filename, e) => write!(f, "{}: {}", filename, e),
+            UniqrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for UniqrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UniqrError::Input(e) => Some(e),
+            UniqrError::OutFile(_, e) => Some(e),
+            UniqrError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<InputError> for UniqrError {
+    fn from(e: InputError) -> Self {
+        UniqrError::Input(e)
+    }
+}
+
+impl From<io::Error> for UniqrError {
+    fn from(e: io::Error) -> Self {
+        UniqrError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
     in_file: String,
     out_file: Option<String>,
     count: bool,
+    terminator: u8,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("uniqr")
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const INFILE: Opt = Opt::operand("infile");
+    const OUTFILE: Opt = Opt::operand("outfile");
+    const COUNT: Opt = Opt::flag("count").short('c').long("count");
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("uniqr")
         .version("0.1.0")
         .author("Denilson Bro")
         .about("Rust uniq")
         .arg(
-            Arg::with_name("infile")
+            INFILE
+                .arg()
                 .value_name("IN_FILE")
                 .help("Input file")
                 .default_value("-"),
         )
-        .arg(
-            Arg::with_name("outfile")
-                .value_name("OUT_FILE")
-                .help("Output file"),
-        )
-        .arg(
-            Arg::with_name("count")
-                .short("c")
-                .help("Show counts")
-                .long("count")
-                .takes_value(false),
-        )
-        .get_matches();
+        .arg(OUTFILE.arg().value_name("OUT_FILE").help("Output file"))
+        .arg(COUNT.arg().help("Show counts"))
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [INFILE, OUTFILE, COUNT, record::OPT];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     // let in_file = matches.value_of_lossy("infile").unwrap().to_string();
     let in_file = matches.value_of_lossy("infile").map(String::from).unwrap();
@@ -47,25 +108,31 @@ pub fn get_args() -> MyResult<Config> {
     //     .and_then(|sq| Some(sq.to_string()));
     let out_file = matches.value_of("outfile").map(String::from);
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         in_file,
         out_file,
         count,
-    })
+        terminator: record::terminator(&matches),
+    }))
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+/// A record without its terminator, which the last one may lack
+fn trim_record(record: &str, terminator: u8) -> &str {
+    if terminator == b'\n' {
+        record.trim_end()
+    } else {
+        record.strip_suffix(terminator as char).unwrap_or(record)
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut file = open(&config.in_file).map_err(|e| format!("{}: {}", config.in_file, e))?;
-    let mut out_file: Box<dyn Write> = match &config.out_file {
-        Some(out_name) => Box::new(File::create(out_name)?),
-        _ => Box::new(stdout()),
+/// Writes to OUT_FILE if one was given, otherwise to `out`.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<()> {
+    let mut file = input::open(&config.in_file)?;
+    let mut out_file: Box<dyn Write + '_> = match &config.out_file {
+        Some(out_name) => Box::new(BufWriter::new(
+            File::create(out_name).map_err(|e| UniqrError::OutFile(out_name.clone(), e))?,
+        )),
+        _ => Box::new(out),
     };
 
     let mut line = String::new();
@@ -85,12 +152,12 @@ pub fn run(config: Config) -> MyResult<()> {
     };
 
     loop {
-        let bytes = file.read_line(&mut line)?;
+        let bytes = record::read_record(&mut file, config.terminator, &mut line)?;
         if bytes == 0 {
             break;
         }
 
-        if line.trim_end() != prev_line.trim_end() {
+        if trim_record(&line, config.terminator) != trim_record(&prev_line, config.terminator) {
             print(count, &prev_line)?;
             prev_line = line.clone();
             count = 0;
@@ -100,6 +167,7 @@ pub fn run(config: Config) -> MyResult<()> {
         line.clear();
     }
     print(count, &prev_line)?;
+    out_file.flush()?;
 
     Ok(())
 }
@@ -1,8 +1,8 @@
-use uniqr;
+use clr_common::output;
 
 fn main() {
-    if let Err(e) = uniqr::get_args().and_then(uniqr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| uniqr::get_args().and_then(|parsed| parsed.run(out, uniqr::run)),
+        1,
+    ));
 }
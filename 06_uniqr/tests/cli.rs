@@ -605,3 +605,15 @@ fn t6_outfile_count() -> TestResult {
 fn t6_stdin_outfile_count() -> TestResult {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-z"])
+        .write_stdin("a\nb\0a\nb\0c\0c")
+        .assert()
+        .success()
+        .stdout("   2 a\nb\0   2 c\0");
+    Ok(())
+}
@@ -1,11 +1,48 @@
 use crate::EntryType::*;
 
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+};
 use regex::Regex;
 use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, Write};
 use walkdir::{DirEntry, WalkDir};
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, FindrError>;
+
+#[derive(Debug)]
+pub enum FindrError {
+    InvalidName(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for FindrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FindrError::InvalidName(name) => write!(f, "Invalid --name \"{}\"", name),
+            FindrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for FindrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FindrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FindrError {
+    fn from(e: io::Error) -> Self {
+        FindrError::Io(e)
+    }
+}
 
 #[derive(Debug, Eq, PartialEq)]
 enum EntryType {
@@ -19,40 +56,71 @@ pub struct Config {
     paths: Vec<String>,
     names: Vec<Regex>,
     entry_types: Vec<EntryType>,
+    print0: bool,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("findr")
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const PATHS: Opt = Opt::operand("paths").multiple();
+    const NAMES: Opt = Opt::option("names").short('n').long("name").multiple();
+    const TYPES: Opt = Opt::option("types").short('t').long("type").multiple();
+    const PRINT0: Opt = Opt::flag("print0").long("print0");
+
+    // find spells it `-print0`, which clap would read as a bundle of shorts
+    let args: Vec<OsString> = args
+        .into_iter()
+        .map(Into::into)
+        .map(|arg| {
+            if arg == "-print0" {
+                "--print0".into()
+            } else {
+                arg
+            }
+        })
+        .collect();
+    let mut app = App::new("findr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust find")
+        .after_help(
+            "EXIT STATUS:\n    0 if every PATH was searched, 1 if any entry could not be read",
+        )
         .arg(
-            Arg::with_name("paths")
+            PATHS
+                .arg()
                 .value_name("PATH")
                 .help("Search paths")
-                .default_value(".")
-                .multiple(true),
-        )
-        .arg(
-            Arg::with_name("names")
-                .short("n")
-                .long("name")
-                .help("Name")
-                .takes_value(true)
-                .value_name("NAME")
-                .multiple(true),
+                .default_value("."),
         )
+        .arg(NAMES.arg().help("Name").value_name("NAME"))
         .arg(
-            Arg::with_name("types")
-                .short("t")
-                .long("type")
+            TYPES
+                .arg()
                 .help("Entry type")
-                .takes_value(true)
                 .value_name("TYPE")
-                .multiple(true)
                 .possible_values(&["f", "d", "l"]),
         )
-        .get_matches();
+        .arg(
+            PRINT0
+                .arg()
+                .help("End each path with NUL instead of newline, as -print0 does"),
+        )
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [PATHS, NAMES, TYPES, PRINT0];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let paths = matches.values_of_lossy("paths").unwrap();
 
@@ -74,20 +142,24 @@ pub fn get_args() -> MyResult<Config> {
         .values_of_lossy("names")
         .map(|vals| {
             vals.into_iter()
-                .map(|name| Regex::new(&name).map_err(|_| format!("Invalid --name \"{}\"", name)))
+                .map(|name| Regex::new(&name).map_err(|_| FindrError::InvalidName(name)))
                 .collect::<Result<Vec<_>, _>>()
         })
         .transpose()?
         .unwrap_or_default();
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         paths,
         names,
         entry_types,
-    })
+        print0: matches.is_present("print0"),
+    }))
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+/// Returns the exit status: 1 if any entry could not be read.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    let mut status = 0;
+
     let type_filter = |entry: &DirEntry| {
         config.entry_types.is_empty()
             || config
@@ -115,6 +187,7 @@ pub fn run(config: Config) -> MyResult<()> {
                 Ok(entry) => Some(entry),
                 Err(e) => {
                     eprintln!("{}", e);
+                    status = 1;
                     None
                 }
             })
@@ -122,8 +195,11 @@ pub fn run(config: Config) -> MyResult<()> {
             .filter(name_filter)
             .map(|entry| entry.path().display().to_string())
             .collect::<Vec<_>>();
-        println!("{}", entries.join("\n"));
+        for entry in entries {
+            write!(out, "{}", entry)?;
+            out.write_all(if config.print0 { b"\0" } else { b"\n" })?;
+        }
     }
 
-    Ok(())
+    Ok(status)
 }
@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = findr::get_args().and_then(findr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| findr::get_args().and_then(|parsed| parsed.run(out, findr::run)),
+        1,
+    ));
 }
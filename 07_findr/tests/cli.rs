@@ -30,11 +30,35 @@ fn skips_bad_dir() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn generate() -> TestResult {
+    for shell in &["bash", "zsh", "fish"] {
+        Command::cargo_bin(PRG)?
+            .args(["--generate", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("f d l"));
+    }
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "man"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(".TH FINDR 1"))
+        .stdout(predicate::str::contains("[possible values: f, d, l]"));
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "ksh"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a valid value"));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_bad_name() -> TestResult {
@@ -302,7 +326,7 @@ fn unreadable_dir() -> TestResult {
     let cmd = Command::cargo_bin(PRG)?
         .arg("tests/inputs")
         .assert()
-        .success();
+        .failure();
     fs::remove_dir(dirname)?;
 
     let out = cmd.get_output();
@@ -316,3 +340,25 @@ fn unreadable_dir() -> TestResult {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn print0() -> TestResult {
+    let contents = fs::read_to_string("tests/expected/path_a_b.txt")?;
+    let mut expected: Vec<&str> = contents.lines().collect();
+    expected.sort();
+
+    for flag in &["-print0", "--print0"] {
+        let cmd = Command::cargo_bin(PRG)?
+            .args(["tests/inputs/a/b", flag])
+            .assert()
+            .success();
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        assert!(stdout.ends_with('\0') && !stdout.contains('\n'));
+
+        let mut paths: Vec<&str> = stdout.split_terminator('\0').collect();
+        paths.sort();
+        assert_eq!(paths, expected);
+    }
+    Ok(())
+}
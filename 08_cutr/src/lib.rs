@@ -1,17 +1,68 @@
-use clap::{App, Arg};
-use csv::{StringRecord, ReaderBuilder, WriterBuilder};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+    input::{self, Input, InputError},
+    record,
+};
+use csv::{ReaderBuilder, StringRecord, Terminator, WriterBuilder};
 use regex::Regex;
 use std::{
     error::Error,
-    fs::File,
-    io::{BufRead, BufReader},
-    ops::{Deref, Index, Range},
-    usize,
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
+    ops::Range,
 };
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, CutrError>;
 type PositionList = Vec<Range<usize>>;
 
+#[derive(Debug)]
+pub enum CutrError {
+    BadDelimiter(String),
+    InvalidList(String),
+    MissingExtract,
+    Csv(csv::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for CutrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CutrError::BadDelimiter(delim) => {
+                write!(f, "--delim \"{}\" must be a single byte", delim)
+            }
+            CutrError::InvalidList(msg) => write!(f, "{}", msg),
+            CutrError::MissingExtract => write!(f, "Must have --fields, --bytes, or --chars"),
+            CutrError::Csv(e) => write!(f, "{}", e),
+            CutrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CutrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CutrError::Csv(e) => Some(e),
+            CutrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<csv::Error> for CutrError {
+    fn from(e: csv::Error) -> Self {
+        CutrError::Csv(e)
+    }
+}
+
+impl From<io::Error> for CutrError {
+    fn from(e: io::Error) -> Self {
+        CutrError::Io(e)
+    }
+}
+
 #[derive(Debug)]
 pub enum Extract {
     Fields(PositionList),
@@ -24,68 +75,72 @@ pub struct Config {
     files: Vec<String>,
     delimiter: u8,
     extract: Extract,
+    terminator: u8,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("cutr")
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILES: Opt = Opt::operand("files").multiple();
+    const DELIM: Opt = Opt::option("delim").short('d').long("delim");
+    const BYTES: Opt = Opt::option("bytes")
+        .short('b')
+        .long("bytes")
+        .conflicts_with(&["chars", "fields", "chars"]);
+    const CHARS: Opt = Opt::option("chars")
+        .short('c')
+        .long("chars")
+        .conflicts_with(&["fields", "bytes"]);
+    const FIELDS: Opt = Opt::option("fields")
+        .short('f')
+        .long("fields")
+        .conflicts_with(&["bytes", "chars"]);
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("cutr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust cut")
+        .after_help("EXIT STATUS:\n    0 if every FILE was read, 1 if any FILE could not be opened")
         .arg(
-            Arg::with_name("files")
+            FILES
+                .arg()
                 .help("Input file(s)")
-                .takes_value(false)
                 .value_name("FILE")
-                .multiple(true)
                 .default_value("-"),
         )
         .arg(
-            Arg::with_name("delim")
-                .short("d")
-                .long("delim")
+            DELIM
+                .arg()
                 .help("Field delimiter")
-                .takes_value(true)
                 .value_name("DELIMETER")
                 .default_value("\t"),
         )
-        .arg(
-            Arg::with_name("bytes")
-                .short("b")
-                .long("bytes")
-                .help("Selected bytes")
-                .takes_value(true)
-                .value_name("BYTES")
-                .conflicts_with("chars")
-                .conflicts_with_all(&["fields", "chars"]),
-        )
-        .arg(
-            Arg::with_name("chars")
-                .short("c")
-                .long("chars")
-                .help("Selected characters")
-                .takes_value(true)
-                .value_name("CHARS")
-                .conflicts_with_all(&["fields", "bytes"]),
-        )
-        .arg(
-            Arg::with_name("fields")
-                .short("f")
-                .long("fields")
-                .help("Selected fields")
-                .takes_value(true)
-                .value_name("FIELDS")
-                .conflicts_with_all(&["bytes", "chars"]),
-        )
-        .get_matches();
+        .arg(BYTES.arg().help("Selected bytes").value_name("BYTES"))
+        .arg(CHARS.arg().help("Selected characters").value_name("CHARS"))
+        .arg(FIELDS.arg().help("Selected fields").value_name("FIELDS"))
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [FILES, DELIM, BYTES, CHARS, FIELDS, record::OPT];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let files = matches.values_of_lossy("files").unwrap();
     let delimiter = matches.value_of_lossy("delim").unwrap();
     let delim_bytes = delimiter.as_bytes();
     if delim_bytes.len() != 1 {
-        return Err(From::from(format!(
-            "--delim \"{}\" must be a single byte",
-            delimiter
-        )));
+        return Err(CutrError::BadDelimiter(delimiter.into_owned()));
     }
 
     let fields = matches.value_of("fields").map(parse_pos).transpose()?;
@@ -99,97 +154,35 @@ pub fn get_args() -> MyResult<Config> {
     } else if let Some(chars_range) = chars {
         Extract::Chars(chars_range)
     } else {
-        return Err("Must have --fields, --bytes, or --chars".into());
+        return Err(CutrError::MissingExtract);
     };
 
     // let ranges = parse_pos(&fields)?;
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         files,
         delimiter: *delim_bytes.first().unwrap(),
         extract: ranges,
-    })
-}
-
-fn parse_pos2(range: &str) -> MyResult<PositionList> {
-    let mut range_vec = Vec::default();
-
-    let extracts = range.split(",");
-
-    for extract in extracts {
-        if extract.contains("+") || extract.contains(char::is_alphabetic) {
-            return Err(format!("illegal list value: \"{}\"", extract).into());
-        }
-
-        let split_extracts = extract.split("-").collect::<Vec<&str>>();
-        if split_extracts.len() == 1 {
-            match extract.parse::<usize>() {
-                Ok(e) if e > 0 => range_vec.push(Range {
-                    start: e - 1,
-                    end: e,
-                }),
-                _ => {
-                    return Err(format!("illegal list value: \"{}\"", range).into());
-                }
-            }
-        } else if split_extracts.len() == 2 {
-            let mut start = 0;
-            let mut end = 0;
-
-            for split_extract in split_extracts {
-                match split_extract.parse::<usize>() {
-                    Ok(e) if e > 0 => {
-                        if start == 0 {
-                            start = e
-                        } else {
-                            end = e
-                        }
-                    }
-                    _ => {
-                        return Err(format!("illegal list value: \"{}\"", split_extract).into());
-                    }
-                }
-            }
-
-            if start >= end {
-                return Err(format!(
-                    "First number in range ({}) must be lower than second number ({})",
-                    start, end
-                )
-                .into());
-            }
-
-            range_vec.push(Range {
-                start: start - 1,
-                end,
-            });
-        } else {
-            return Err(format!("illegal list value: \"{}\"", range).into());
-        }
-    }
-
-    Ok(range_vec)
-    // Vec<Range<usize>>
+        terminator: record::terminator(&matches),
+    }))
 }
 
 fn parse_index(input: &str) -> Result<usize, String> {
     let value_error = || format!("illegal list value: \"{}\"", input);
-    input
-        .starts_with("+")
-        .then(|| Err(value_error()))
-        .unwrap_or_else(|| {
-            input
-                .parse::<std::num::NonZeroUsize>()
-                .map(|n| usize::from(n) - 1)
-                .map_err(|_| value_error())
-        })
+    if input.starts_with('+') {
+        Err(value_error())
+    } else {
+        input
+            .parse::<std::num::NonZeroUsize>()
+            .map(|n| usize::from(n) - 1)
+            .map_err(|_| value_error())
+    }
 }
 
 fn parse_pos(range: &str) -> MyResult<PositionList> {
     let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap();
     range
         .split(',')
-        .into_iter()
         .map(|pos_list| {
             parse_index(pos_list).map(|n| n..n + 1).or_else(|e| {
                 range_re.captures(pos_list).ok_or(e).and_then(|captures| {
@@ -207,20 +200,11 @@ fn parse_pos(range: &str) -> MyResult<PositionList> {
             })
         })
         .collect::<Result<_, _>>()
-        .map_err(From::from)
-}
-
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(std::io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
+        .map_err(CutrError::InvalidList)
 }
 
-
 fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     let chars: Vec<_> = line.chars().collect();
-    let mut entries: Vec<char> = vec![];
     // for pos in char_pos.iter().cloned() {
     //     // for i in pos {
     //     //     if let Some(val) = chars.get(i) {
@@ -422,37 +406,73 @@ mod unit_test {
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    for filename in &config.files {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                match &config.extract {
-                    Extract::Fields(field_pos) => {
-                        let mut reader = ReaderBuilder::new()
-                            .delimiter(config.delimiter)
-                            .has_headers(false)
-                            .from_reader(file);
-                        let mut wtr = WriterBuilder::new()
-                            .delimiter(config.delimiter)
-                            .from_writer(std::io::stdout());
-                        for record in reader.records() {
-                            wtr.write_record(extract_fields(&record?, field_pos))?;
-                        }
-                    }
-                    Extract::Bytes(byte_pos) => {
-                        for line in file.lines() {
-                            println!("{}", extract_bytes(&line?, byte_pos));
-                        }
-                    }
-                    Extract::Chars(char_pos) => {
-                        for line in file.lines() {
-                            println!("{}", extract_chars(&line?, char_pos));
-                        }
-                    }
-                }
+/// A csv error from reading `filename`, named after it like the errors of
+/// `Input` are
+fn read_error(filename: &str, e: csv::Error) -> io::Error {
+    if !e.is_io_error() {
+        let e = io::Error::new(io::ErrorKind::InvalidData, e);
+        return io::Error::new(e.kind(), InputError::new(filename, e));
+    }
+    match e.into_kind() {
+        // `Input` has named it already
+        csv::ErrorKind::Io(e) => e,
+        _ => unreachable!(),
+    }
+}
+
+fn cut(config: &Config, filename: &str, file: Input, out: &mut impl Write) -> MyResult<()> {
+    match &config.extract {
+        Extract::Fields(field_pos) => {
+            let mut reader = ReaderBuilder::new()
+                .delimiter(config.delimiter)
+                .terminator(match config.terminator {
+                    b'\n' => Terminator::CRLF,
+                    terminator => Terminator::Any(terminator),
+                })
+                .has_headers(false)
+                .from_reader(file);
+            let mut wtr = WriterBuilder::new()
+                .delimiter(config.delimiter)
+                .terminator(Terminator::Any(config.terminator))
+                .from_writer(&mut *out);
+            for record in reader.records() {
+                let record = record.map_err(|e| read_error(filename, e))?;
+                wtr.write_record(extract_fields(&record, field_pos))?;
+            }
+            wtr.flush()?;
+        }
+        Extract::Bytes(byte_pos) => {
+            for line in record::records(file, config.terminator) {
+                write!(out, "{}", extract_bytes(&line?, byte_pos))?;
+                out.write_all(&[config.terminator])?;
+            }
+        }
+        Extract::Chars(char_pos) => {
+            for line in record::records(file, config.terminator) {
+                write!(out, "{}", extract_chars(&line?, char_pos))?;
+                out.write_all(&[config.terminator])?;
             }
         }
     }
     Ok(())
 }
+
+/// Returns the exit status: 1 if any file could not be opened or read.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    let mut status = 0;
+    for filename in &config.files {
+        match input::open(filename).map(|file| cut(&config, filename, file, out)) {
+            Err(err) => {
+                eprintln!("{}", err);
+                status = 1;
+            }
+            // the error names the file; go on to the next one
+            Ok(Err(CutrError::Io(err))) if input::is_read_error(&err) => {
+                eprintln!("{}", err);
+                status = 1;
+            }
+            Ok(cut) => cut?,
+        }
+    }
+    Ok(status)
+}
@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = cutr::get_args().and_then(cutr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| cutr::get_args().and_then(|parsed| parsed.run(out, cutr::run)),
+        1,
+    ));
 }
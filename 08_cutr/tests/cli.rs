@@ -37,7 +37,7 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .args(&["-f", "1", CSV, &bad, TSV])
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
@@ -337,3 +337,40 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-d", ",", "-f", "2"])
+        .write_stdin("a,b\nc\0d,e\0")
+        .assert()
+        .success()
+        .stdout("b\nc\0e\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-c", "1-2"])
+        .write_stdin("abc\ndef\0ghi")
+        .assert()
+        .success()
+        .stdout("ab\0gh\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_unreadable_file() -> TestResult {
+    let truncated = "tests/inputs/truncated.csv.gz";
+    for extract in ["-f", "-b"] {
+        let first = Command::cargo_bin(PRG)?
+            .args(["-d", ",", extract, "1", CSV])
+            .output()?
+            .stdout;
+        Command::cargo_bin(PRG)?
+            .args(["-d", ",", extract, "1", truncated, CSV])
+            .assert()
+            .code(1)
+            .stderr(format!("{}: incomplete deflate stream\n", truncated))
+            .stdout(first);
+    }
+    Ok(())
+}
@@ -1,15 +1,66 @@
 use std::{
     error::Error,
-    fs::File,
-    io::{BufRead, BufReader},
-    result,
+    ffi::OsString,
+    fmt,
+    io::{self, BufRead},
 };
 
-use clap::{App, Arg};
-use regex::{Regex, RegexBuilder};
-use walkdir::{DirEntry, WalkDir};
+use ansi_term::Colour;
+use clap::{App, ErrorKind};
+use clr_common::{
+    color::{self, ColorChoice},
+    config::{self, Opt, Parsed},
+    generate, input,
+    output::Sink,
+    record,
+};
+use regex::{Captures, Regex, RegexBuilder};
+use walkdir::WalkDir;
+
+type MyResult<T> = Result<T, GreprError>;
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+/// Exit status when at least one line was selected
+pub const EXIT_MATCH: i32 = 0;
+/// Exit status when no lines were selected
+pub const EXIT_NO_MATCH: i32 = 1;
+/// Exit status when an error occurred, including usage errors
+pub const EXIT_ERROR: i32 = 2;
+
+#[derive(Debug)]
+pub enum GreprError {
+    Usage(clap::Error),
+    InvalidPattern(String),
+    IsDirectory(String),
+    Walk(walkdir::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for GreprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GreprError::Usage(e) => write!(f, "{}", e),
+            GreprError::InvalidPattern(pattern) => write!(f, "Invalid pattern \"{}\"", pattern),
+            GreprError::IsDirectory(path) => write!(f, "{} is a directory", path),
+            GreprError::Walk(e) => write!(f, "{}", e),
+            GreprError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for GreprError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GreprError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GreprError {
+    fn from(e: io::Error) -> Self {
+        GreprError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
@@ -18,71 +69,95 @@ pub struct Config {
     recursive: bool,
     count: bool,
     invert_match: bool,
+    color: ColorChoice,
+    terminator: u8,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("grepr")
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const PATTERN: Opt = Opt::operand("pattern");
+    const FILES: Opt = Opt::operand("files").multiple();
+    const COUNT: Opt = Opt::flag("count").short('c').long("count");
+    const INSENSITIVE: Opt = Opt::flag("insensitive").short('i').long("insensitive");
+    const INVERT_MATCH: Opt = Opt::flag("invert-match").short('v').long("invert-match");
+    const RECURSIVE: Opt = Opt::flag("recursive").short('r').long("recursive");
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("grepr")
         .version("0.1.0")
         .author("Denilson Bro <denilson020898@gmail.com>")
         .about("Rust grep")
+        .after_help(
+            "EXIT STATUS:\n    0 if a line is selected, 1 if no lines were selected, \
+             2 if an error occurred",
+        )
         .arg(
-            Arg::with_name("pattern")
+            PATTERN
+                .arg()
                 .help("Search pattern")
                 .value_name("PATTERN")
-                .required(true),
+                .required_unless("print_config"),
         )
         .arg(
-            Arg::with_name("files")
+            FILES
+                .arg()
                 .help("Input file(s)")
                 .value_name("FILE")
-                .multiple(true)
                 .default_value("-"),
         )
-        .arg(
-            Arg::with_name("count")
-                .short("c")
-                .long("count")
-                .help("Count occurrences")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("insensitive")
-                .short("i")
-                .long("insensitive")
-                .help("Case-insensitive")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("invert-match")
-                .short("v")
-                .long("invert-match")
-                .help("Invert match")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("recursive")
-                .short("r")
-                .long("recursive")
-                .help("Recursive search")
-                .takes_value(false),
-        )
-        .get_matches();
+        .arg(COUNT.arg().help("Count occurrences"))
+        .arg(INSENSITIVE.arg().help("Case-insensitive"))
+        .arg(INVERT_MATCH.arg().help("Invert match"))
+        .arg(RECURSIVE.arg().help("Recursive search"))
+        .arg(color::arg().help("Highlight matches and file names"))
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [
+        PATTERN,
+        FILES,
+        COUNT,
+        INSENSITIVE,
+        INVERT_MATCH,
+        RECURSIVE,
+        color::OPT,
+        record::OPT,
+    ];
+    let matches = match config::get_matches_safe(app, &opts, args) {
+        Ok(Parsed::Run(matches)) => matches,
+        Ok(Parsed::PrintConfig(settings)) => return Ok(Parsed::PrintConfig(settings)),
+        Err(e) => match e.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+            _ => return Err(GreprError::Usage(e)),
+        },
+    };
 
     let pattern = matches.value_of("pattern").unwrap();
 
     let pattern = RegexBuilder::new(pattern)
         .case_insensitive(matches.is_present("insensitive"))
         .build()
-        .map_err(|_| format!("Invalid pattern \"{}\"", pattern))?;
+        .map_err(|_| GreprError::InvalidPattern(pattern.to_string()))?;
     let files = matches.values_of_lossy("files").unwrap();
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         pattern,
         files,
         recursive: matches.is_present("recursive"),
         count: matches.is_present("count"),
         invert_match: matches.is_present("invert-match"),
-    })
+        color: color::choice(&matches),
+        terminator: record::terminator(&matches),
+    }))
 }
 
 fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
@@ -101,22 +176,22 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
                             let is_dir = e.path().is_dir();
                             let path = e.path().display().to_string();
                             if !recursive && is_dir {
-                                return Some(Err(format!("{} is a directory", path).into()));
+                                return Some(Err(GreprError::IsDirectory(path)));
                             } else if recursive && is_dir {
                                 return None;
                             }
                             Some(Ok(path))
                         }
-                        Err(e) => Some(Err(e.into())),
+                        Err(e) => Some(Err(GreprError::Walk(e))),
                     })
                     .collect::<Vec<_>>();
 
-                results.extend(walked_path.into_iter())
+                results.extend(walked_path)
             }
         }
     }
 
-    return results;
+    results
 
     // paths
     //     .into_iter()
@@ -142,17 +217,11 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
     //     .collect::<Vec<_>>()
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(std::io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
-}
-
 fn find_lines<T: BufRead>(
     mut file: T,
     pattern: &Regex,
     invert_match: bool,
+    terminator: u8,
 ) -> MyResult<Vec<String>> {
     // let mut matches = vec![];
     // let mut inv_matches = vec![];
@@ -178,7 +247,7 @@ fn find_lines<T: BufRead>(
     let mut line = String::new();
 
     loop {
-        let read_bytes = file.read_line(&mut line)?;
+        let read_bytes = record::read_record(&mut file, terminator, &mut line)?;
         if read_bytes == 0 {
             break;
         }
@@ -192,13 +261,93 @@ fn find_lines<T: BufRead>(
     Ok(matches)
 }
 
+/// Paint every non-empty match of `pattern` in `line` the way GNU grep does
+fn highlight(line: &str, pattern: &Regex) -> String {
+    pattern
+        .replace_all(line, |caps: &Captures| match &caps[0] {
+            "" => String::new(),
+            text => Colour::Red.bold().paint(text).to_string(),
+        })
+        .into_owned()
+}
+
+/// Returns the exit status: `EXIT_MATCH` if any line was selected,
+/// `EXIT_NO_MATCH` if none were and `EXIT_ERROR` if any file failed.
+pub fn run(config: Config, out: &mut impl Sink) -> MyResult<i32> {
+    let color = color::enabled(config.color, out);
+    let mut failed = false;
+    let mut selected = false;
+    let entries = find_files(&config.files, config.recursive);
+    for entry in entries {
+        match entry {
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+            }
+            Ok(filename) => match input::open(&filename) {
+                Err(e) => {
+                    eprintln!("{}", e);
+                    failed = true;
+                }
+                Ok(file) => {
+                    let matches = match find_lines(
+                        file,
+                        &config.pattern,
+                        config.invert_match,
+                        config.terminator,
+                    ) {
+                        Ok(matches) => matches,
+                        // the read error names the file; go on to the next one
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            failed = true;
+                            continue;
+                        }
+                    };
+                    selected |= !matches.is_empty();
+                    let fname = if !(config.recursive || config.files.len() > 1) {
+                        "".to_string()
+                    } else if color {
+                        format!(
+                            "{}{}",
+                            Colour::Purple.paint(filename),
+                            Colour::Cyan.paint(":")
+                        )
+                    } else {
+                        format!("{}:", filename)
+                    };
+                    if config.count {
+                        writeln!(out, "{}{}", fname, matches.len())?;
+                    } else {
+                        for line in matches {
+                            if color && !config.invert_match {
+                                write!(out, "{}{}", fname, highlight(&line, &config.pattern))?;
+                            } else {
+                                write!(out, "{}{}", fname, line)?;
+                            }
+                        }
+                    }
+                }
+            },
+        }
+    }
+
+    Ok(if failed {
+        EXIT_ERROR
+    } else if selected {
+        EXIT_MATCH
+    } else {
+        EXIT_NO_MATCH
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
 
     use crate::find_lines;
 
-    use super::find_files;
+    use super::{find_files, highlight};
     use rand::{distributions::Alphanumeric, Rng};
     use regex::{Regex, RegexBuilder};
 
@@ -250,12 +399,12 @@ mod tests {
 
         // pattern "or" should match the one line, "Lorem"
         let re1 = Regex::new("or").unwrap();
-        let matches = find_lines(Cursor::new(&text), &re1, false);
+        let matches = find_lines(Cursor::new(&text), &re1, false, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
 
         // When inverted, the function should match the other two lines
-        let matches = find_lines(Cursor::new(&text), &re1, true);
+        let matches = find_lines(Cursor::new(&text), &re1, true, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
@@ -264,40 +413,31 @@ mod tests {
             .build()
             .unwrap();
 
-        let matches = find_lines(Cursor::new(&text), &re2, false);
+        let matches = find_lines(Cursor::new(&text), &re2, false, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
-        let matches = find_lines(Cursor::new(&text), &re2, true);
+        let matches = find_lines(Cursor::new(&text), &re2, true, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
+
+        // with NUL terminators a record may span several lines
+        let text = b"Lorem\nIpsum\0DOLOR\0";
+        let matches = find_lines(Cursor::new(&text), &re1, false, b'\0');
+        assert_eq!(matches.unwrap(), vec!["Lorem\nIpsum\0"]);
     }
-}
 
-pub fn run(config: Config) -> MyResult<()> {
-    let entries = find_files(&config.files, config.recursive);
-    for entry in entries {
-        match entry {
-            Err(e) => eprintln!("{}", e),
-            Ok(filename) => match open(&filename) {
-                Err(e) => eprintln!("{}: {}", filename, e),
-                Ok(file) => {
-                    let matches = find_lines(file, &config.pattern, config.invert_match)?;
-                    let fname = if config.recursive || config.files.len() > 1 {
-                        format!("{}:", filename)
-                    } else {
-                        "".to_string()
-                    };
-                    if config.count {
-                        println!("{}{}", fname, matches.len());
-                    } else {
-                        for line in matches {
-                            print!("{}{}", fname, line);
-                        }
-                    }
-                }
-            },
-        }
+    #[test]
+    fn test_highlight() {
+        let re = Regex::new("o").unwrap();
+        assert_eq!(
+            highlight("foo\n", &re),
+            "f\u{1b}[1;31mo\u{1b}[0m\u{1b}[1;31mo\u{1b}[0m\n"
+        );
+        assert_eq!(highlight("bar\n", &re), "bar\n");
+
+        // empty matches are left alone
+        let re = Regex::new("").unwrap();
+        assert_eq!(highlight("bar\n", &re), "bar\n");
     }
-    Ok(())
 }
@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = grepr::get_args().and_then(grepr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| grepr::get_args().and_then(|parsed| parsed.run(out, grepr::run)),
+        grepr::EXIT_ERROR,
+    ));
 }
@@ -8,6 +8,7 @@ type TestResult = Result<(), Box<dyn std::error::Error>>;
 
 const PRG: &str = "grepr";
 const BUSTLE: &str = "tests/inputs/bustle.txt";
+const BUSTLE_GZ: &str = "tests/compressed/bustle.txt.gz";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const NOBODY: &str = "tests/inputs/nobody.txt";
@@ -44,7 +45,7 @@ fn dies_bad_pattern() -> TestResult {
     Command::cargo_bin(PRG)?
         .args(&["*foo", FOX])
         .assert()
-        .failure()
+        .code(2)
         .stderr(predicate::str::contains("Invalid pattern \"*foo\""));
     Ok(())
 }
@@ -57,10 +58,121 @@ fn warns_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .args(&["foo", &bad])
         .assert()
+        .code(2)
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn exit_status() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(&["fox", FOX])
+        .assert()
+        .code(0);
+    Command::cargo_bin(PRG)?
+        .args(&["nobody", FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Command::cargo_bin(PRG)?
+        .args(&["fox", FOX, "tests/inputs"])
+        .assert()
+        .code(2);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown \u{1b}[1;31mfox\u{1b}[0m jumps over the lazy dog.\n");
+    Command::cargo_bin(PRG)?
+        .args(["--color=never", "fox", FOX])
+        .env("CLICOLOR_FORCE", "1")
+        .assert()
+        .success()
+        .stdout("The quick brown fox jumps over the lazy dog.\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn config_file() -> TestResult {
+    let fox = "The quick brown fox jumps over the lazy dog.\n";
+    Command::cargo_bin(PRG)?
+        .args(["FOX", FOX])
+        .env("XDG_CONFIG_HOME", "tests/config")
+        .env_remove("GREPR_OPTS")
+        .assert()
+        .success()
+        .stdout(fox);
+
+    // the environment adds to the file, the command line overrides both
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "FOX", FOX])
+        .env("XDG_CONFIG_HOME", "tests/config")
+        .env("GREPR_OPTS", "-c --color=never")
+        .assert()
+        .success()
+        .stdout("1\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["--no-config", "FOX", FOX])
+        .env("XDG_CONFIG_HOME", "tests/config")
+        .env("GREPR_OPTS", "-c")
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print_config() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--print-config", "-v"])
+        .env("XDG_CONFIG_HOME", "tests/config")
+        .env("GREPR_OPTS", "--count")
+        .assert()
+        .success()
+        .stdout(
+            "# from tests/config/clr/grepr.toml\n\
+             # from GREPR_OPTS=\"--count\"\n\
+             count = true\n\
+             insensitive = true\n\
+             invert-match = true\n\
+             recursive = false\n\
+             color = \"never\"\n\
+             zero-terminated = false\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_config() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["fox", FOX])
+        .env("XDG_CONFIG_HOME", "tests/config/bad")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "tests/config/bad/clr/grepr.toml: line 1: invalid string",
+        ));
+    Command::cargo_bin(PRG)?
+        .args(["fox", FOX])
+        .env("XDG_CONFIG_HOME", "tests/config")
+        .env("GREPR_OPTS", "-e 'unterminated")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("GREPR_OPTS: unterminated quote"));
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let windows_file = format!("{}.windows", expected_file);
@@ -102,6 +214,15 @@ fn bustle_capitalized() -> TestResult {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn bustle_gz() -> TestResult {
+    run(
+        &["The", BUSTLE_GZ],
+        "tests/expected/bustle.txt.the.capitalized",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn bustle_lowercase() -> TestResult {
@@ -277,3 +398,42 @@ fn stdin_insensitive_count() -> TestResult {
         .stdout(expected);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "fox"])
+        .write_stdin("the\nfox\0the dog\0a fox\0")
+        .assert()
+        .success()
+        .stdout("the\nfox\0a fox\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["fox", "-", FOX])
+        .write_stdin(b"the \xff fox\n".to_vec())
+        .assert()
+        .success()
+        .stdout(
+            "-:the \u{fffd} fox\n\
+             tests/inputs/fox.txt:The quick brown fox jumps over the lazy dog.\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn read_error_goes_on() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["fox", "tests/compressed/truncated.txt.gz", FOX])
+        .assert()
+        .code(2)
+        .stderr("tests/compressed/truncated.txt.gz: incomplete deflate stream\n")
+        .stdout("tests/inputs/fox.txt:The quick brown fox jumps over the lazy dog.\n");
+    Ok(())
+}
@@ -1,11 +1,58 @@
 use std::{
     error::Error,
-    io::{BufRead, BufReader},
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
 };
 
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+    input::{self, InputError},
+    record,
+};
+
+type MyResult<T> = Result<T, CommrError>;
+
+#[derive(Debug)]
+pub enum CommrError {
+    BothStdin,
+    Input(InputError),
+    Io(io::Error),
+}
+
+impl fmt::Display for CommrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommrError::BothStdin => write!(f, "Both input files cannot be STDIN (\"-\")"),
+            CommrError::Input(e) => write!(f, "{}", e),
+            CommrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CommrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CommrError::BothStdin => None,
+            CommrError::Input(e) => Some(e),
+            CommrError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for CommrError {
+    fn from(e: io::Error) -> Self {
+        CommrError::Io(e)
+    }
+}
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+impl From<InputError> for CommrError {
+    fn from(e: InputError) -> Self {
+        CommrError::Input(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
@@ -16,6 +63,7 @@ pub struct Config {
     show_col3: bool,
     insensitive: bool,
     delimiter: String,
+    terminator: u8,
 }
 
 enum Column<'a> {
@@ -24,59 +72,76 @@ enum Column<'a> {
     Col3(&'a str),
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("commr")
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILE1: Opt = Opt::operand("file1");
+    const FILE2: Opt = Opt::operand("file2");
+    const DELIM: Opt = Opt::option("delim").short('d').long("output-delimiter");
+    const INSENSITIVE: Opt = Opt::flag("insensitive").short('i');
+    const SUPPRESS_COL1: Opt = Opt::flag("suppress_col1").short('1');
+    const SUPPRESS_COL2: Opt = Opt::flag("suppress_col2").short('2');
+    const SUPPRESS_COL3: Opt = Opt::flag("suppress_col3").short('3');
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("commr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust comm")
         .arg(
-            Arg::with_name("file1")
+            FILE1
+                .arg()
                 .value_name("FILE1")
                 .help("Input file 1")
-                .takes_value(true)
-                .required(true),
+                .required_unless("print_config"),
         )
         .arg(
-            Arg::with_name("file2")
+            FILE2
+                .arg()
                 .value_name("FILE2")
                 .help("Input file 2")
-                .takes_value(true)
-                .required(true),
+                .required_unless("print_config"),
         )
         .arg(
-            Arg::with_name("delim")
+            DELIM
+                .arg()
                 .value_name("DELIM")
-                .long("output-delimiter")
-                .short("d")
                 .help("Output delimiter")
-                .takes_value(true)
                 .default_value("\t"),
         )
         .arg(
-            Arg::with_name("insensitive")
-                .short("i")
-                .help("Case-insensitive comparison of lines")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("suppress_col1")
-                .short("1")
-                .help("Suppress printing of column 1")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("suppress_col2")
-                .short("2")
-                .help("Suppress printing of column 2")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("suppress_col3")
-                .short("3")
-                .help("Suppress printing of column 3")
-                .takes_value(false),
+            INSENSITIVE
+                .arg()
+                .help("Case-insensitive comparison of lines"),
         )
-        .get_matches();
+        .arg(SUPPRESS_COL1.arg().help("Suppress printing of column 1"))
+        .arg(SUPPRESS_COL2.arg().help("Suppress printing of column 2"))
+        .arg(SUPPRESS_COL3.arg().help("Suppress printing of column 3"))
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [
+        FILE1,
+        FILE2,
+        DELIM,
+        INSENSITIVE,
+        SUPPRESS_COL1,
+        SUPPRESS_COL2,
+        SUPPRESS_COL3,
+        record::OPT,
+    ];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let file1 = matches.value_of("file1").unwrap().to_string();
     let file2 = matches.value_of("file2").unwrap().to_string();
@@ -87,7 +152,7 @@ pub fn get_args() -> MyResult<Config> {
     let show_col3 = !matches.is_present("suppress_col3");
     let insensitive = matches.is_present("insensitive");
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         file1,
         file2,
         show_col1,
@@ -95,23 +160,15 @@ pub fn get_args() -> MyResult<Config> {
         show_col3,
         insensitive,
         delimiter,
-    })
-}
-
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(std::io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(
-            std::fs::File::open(filename).map_err(|e| format!("{}: {}", filename, e))?,
-        ))),
-    }
+        terminator: record::terminator(&matches),
+    }))
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<()> {
     let file1 = &config.file1;
     let file2 = &config.file2;
     if file1 == "-" && file2 == "-" {
-        return Err(From::from("Both input files cannot be STDIN (\"-\")"));
+        return Err(CommrError::BothStdin);
     }
 
     let case = |line: String| {
@@ -122,7 +179,7 @@ pub fn run(config: Config) -> MyResult<()> {
         }
     };
 
-    let print = |col: Column| {
+    let mut print = |col: Column| -> MyResult<()> {
         let mut columns = vec![];
         match col {
             Column::Col1(val) => {
@@ -152,12 +209,18 @@ pub fn run(config: Config) -> MyResult<()> {
         };
 
         if !columns.is_empty() {
-            println!("{}", columns.join(&config.delimiter))
+            write!(out, "{}", columns.join(&config.delimiter))?;
+            out.write_all(&[config.terminator])?;
         }
+        Ok(())
     };
 
-    let mut lines1 = open(file1)?.lines().filter_map(Result::ok).map(case);
-    let mut lines2 = open(file2)?.lines().filter_map(Result::ok).map(case);
+    let mut lines1 = record::records(input::open(file1)?, config.terminator)
+        .filter_map(Result::ok)
+        .map(case);
+    let mut lines2 = record::records(input::open(file2)?, config.terminator)
+        .filter_map(Result::ok)
+        .map(case);
 
     let mut line1 = lines1.next();
     let mut line2 = lines2.next();
@@ -167,29 +230,29 @@ pub fn run(config: Config) -> MyResult<()> {
             (Some(val1), Some(val2)) => match val1.cmp(val2) {
                 std::cmp::Ordering::Equal => {
                     // println!("{}", val1);
-                    print(Column::Col3(val1));
+                    print(Column::Col3(val1))?;
                     line1 = lines1.next();
                     line2 = lines2.next();
                 }
                 std::cmp::Ordering::Less => {
                     // println!("{}", val1);
-                    print(Column::Col1(val1));
+                    print(Column::Col1(val1))?;
                     line1 = lines1.next();
                 }
                 std::cmp::Ordering::Greater => {
                     // println!("{}", val2);
-                    print(Column::Col2(val2));
+                    print(Column::Col2(val2))?;
                     line2 = lines2.next();
                 }
             },
             (Some(val1), None) => {
                 // println!("{}", val1);
-                print(Column::Col1(val1));
+                print(Column::Col1(val1))?;
                 line1 = lines1.next();
             }
             (None, Some(val2)) => {
                 // println!("{}", val2);
-                print(Column::Col2(val2));
+                print(Column::Col2(val2))?;
                 line2 = lines2.next();
             }
             _ => (),
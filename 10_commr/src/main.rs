@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = commr::get_args().and_then(commr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| commr::get_args().and_then(|parsed| parsed.run(out, commr::run)),
+        1,
+    ));
 }
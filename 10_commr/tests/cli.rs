@@ -345,3 +345,15 @@ fn blank_file1() -> TestResult {
 //fn file1_blanks() -> TestResult {
 //    run(&[FILE1, BLANKS], "tests/expected/file1_blanks.out")
 //}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-", "tests/inputs/nul.txt"])
+        .write_stdin("a\nb\0d\0")
+        .assert()
+        .success()
+        .stdout("\t\ta\nb\0\tc\0d\0");
+    Ok(())
+}
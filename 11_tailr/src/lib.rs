@@ -1,18 +1,52 @@
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate, record,
+};
 use num::Zero;
-use once_cell::sync::OnceCell;
-use regex::Regex;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
-use std::ops::Deref;
-use std::{error::Error, fs::File};
+use std::io::Write;
+use std::{error::Error, ffi::OsString, fmt, fs::File, io};
 
 // static NUM_RE: OnceCell<Regex> = OnceCell::new();
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, TailrError>;
+
+#[derive(Debug)]
+pub enum TailrError {
+    IllegalLineCount(String),
+    IllegalByteCount(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for TailrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TailrError::IllegalLineCount(val) => write!(f, "illegal line count -- {}", val),
+            TailrError::IllegalByteCount(val) => write!(f, "illegal byte count -- {}", val),
+            TailrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for TailrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TailrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TailrError {
+    fn from(e: io::Error) -> Self {
+        TailrError::Io(e)
+    }
+}
 
 #[derive(Debug, PartialEq)]
 enum TakeValue {
@@ -26,44 +60,58 @@ pub struct Config {
     lines: TakeValue,
     bytes: Option<TakeValue>,
     quiet: bool,
+    terminator: u8,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("tailr")
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILE: Opt = Opt::operand("file").multiple();
+    const QUIET: Opt = Opt::flag("quiet").short('q').long("quiet");
+    const BYTES: Opt = Opt::option("bytes")
+        .short('c')
+        .long("bytes")
+        .conflicts_with(&["lines"]);
+    const LINES: Opt = Opt::option("lines").short('n').long("lines");
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("tailr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust tail")
+        .after_help("EXIT STATUS:\n    0 if every FILE was read, 1 if any FILE could not be opened")
         // something
         .arg(
-            Arg::with_name("file")
+            FILE.arg()
                 .value_name("FILE")
                 .help("Input file(s)")
-                .multiple(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("quiet")
-                .short("q")
-                .long("quiet")
-                .help("Suppress headers"),
-        )
-        .arg(
-            Arg::with_name("bytes")
-                .short("c")
-                .long("bytes")
-                .help("Number of bytes")
-                .value_name("BYTES")
-                .conflicts_with("lines"),
+                .required_unless("print_config"),
         )
+        .arg(QUIET.arg().help("Suppress headers"))
+        .arg(BYTES.arg().help("Number of bytes").value_name("BYTES"))
         .arg(
-            Arg::with_name("lines")
-                .short("n")
-                .long("lines")
+            LINES
+                .arg()
                 .help("Number of lines")
                 .value_name("LINES")
                 .default_value("10"),
         )
-        .get_matches();
+        .arg(record::arg())
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [FILE, QUIET, BYTES, LINES, record::OPT];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let files = matches.values_of_lossy("file").unwrap();
     let quiet = matches.is_present("quiet");
@@ -72,24 +120,25 @@ pub fn get_args() -> MyResult<Config> {
         .value_of("bytes")
         .map(parse_take_num)
         .transpose()
-        .map_err(|e| format!("illegal byte count -- {}", e))?;
+        .map_err(TailrError::IllegalByteCount)?;
 
     let lines = matches
         .value_of("lines")
         .map(parse_take_num)
         .transpose()
-        .map_err(|e| format!("illegal line count -- {}", e))?
+        .map_err(TailrError::IllegalLineCount)?
         .unwrap();
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         files,
         lines,
         bytes,
         quiet,
-    })
+        terminator: record::terminator(&matches),
+    }))
 }
 
-fn parse_take_num(value: &str) -> MyResult<TakeValue> {
+fn parse_take_num(value: &str) -> Result<TakeValue, String> {
     match value.parse::<i64>() {
         Ok(_) if value == "+0" => Ok(TakeValue::PlusZero),
         Ok(n) => Ok(TakeValue::TakeNum(
@@ -123,15 +172,15 @@ fn parse_take_num(value: &str) -> MyResult<TakeValue> {
 //     }
 // }
 
-fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
+fn count_lines_bytes(filename: &str, terminator: u8) -> MyResult<(i64, i64)> {
     let file = File::open(filename)?;
     let mut filehandle = BufReader::new(file);
 
     let mut num_lines = 0;
     let mut num_bytes = 0;
 
-    let mut buf = String::new();
-    while let Ok(read_bytes) = filehandle.read_line(&mut buf) {
+    let mut buf = Vec::new();
+    while let Ok(read_bytes) = filehandle.read_until(terminator, &mut buf) {
         if read_bytes == 0 {
             break;
         }
@@ -143,18 +192,24 @@ fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
     Ok((num_lines, num_bytes))
 }
 
-fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: i64) -> MyResult<()> {
+fn print_lines(
+    mut file: impl BufRead,
+    num_lines: &TakeValue,
+    total_lines: i64,
+    terminator: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
     if let Some(start) = get_start_index(num_lines, total_lines) {
         let mut line_num = 0;
         let mut buf = Vec::new();
         loop {
-            let bytes_read = file.read_until(b'\n', &mut buf)?;
+            let bytes_read = file.read_until(terminator, &mut buf)?;
             if bytes_read == 0 {
                 break;
             }
 
             if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+                write!(out, "{}", String::from_utf8_lossy(&buf))?;
             }
             line_num += 1;
 
@@ -168,13 +223,14 @@ fn print_bytes<T: Read + Seek>(
     mut file: T,
     num_bytes: &TakeValue,
     total_lines: i64,
+    out: &mut impl Write,
 ) -> MyResult<()> {
     if let Some(start) = get_start_index(num_bytes, total_lines) {
         file.seek(SeekFrom::Start(start))?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
         if !buf.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buf));
+            write!(out, "{}", String::from_utf8_lossy(&buf))?;
         }
     }
     Ok(())
@@ -188,53 +244,65 @@ fn get_start_index(take_val: &TakeValue, total: i64) -> Option<u64> {
         TakeValue::PlusZero => {
             // let start_index = total as u64 - 1;
             if total > 0 {
-                return Some(0)
+                return Some(0);
             }
             let start_index = total as u64 - 1;
-            return Some(start_index);
+            Some(start_index)
         }
         TakeValue::TakeNum(ref num) => {
             if num.is_zero() || *num > total {
-                return None;
+                None
             } else if num.is_negative() {
-
                 if num.abs() > total {
                     return Some(0);
                 }
 
                 let start_index = total + num;
-                return Some(start_index as u64);
+                Some(start_index as u64)
             } else {
                 let start_index = *num as u64 - 1;
-                return Some(start_index);
+                Some(start_index)
             }
         }
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+/// Returns the exit status: 1 if any file could not be opened.
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    let mut status = 0;
     let num_files = config.files.len();
     for (file_num, filename) in config.files.iter().enumerate() {
-        match File::open(&filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
+        match File::open(filename) {
+            Err(err) => {
+                eprintln!("{}: {}", filename, err);
+                status = 1;
+            }
             Ok(file) => {
                 if !config.quiet && num_files > 1 {
-                    println!("{}==> {} <==",
+                    writeln!(
+                        out,
+                        "{}==> {} <==",
                         if file_num > 0 { "\n" } else { "" },
                         filename
-                    );
+                    )?;
                 }
 
-                let (total_lines, total_bytes) = count_lines_bytes(&filename)?;
+                let (total_lines, total_bytes) = count_lines_bytes(filename, config.terminator)?;
                 if let Some(num_bytes) = &config.bytes {
-                    print_bytes(BufReader::new(file), &num_bytes, total_bytes)?;
+                    print_bytes(BufReader::new(file), num_bytes, total_bytes, out)?;
                 } else {
-                    print_lines(BufReader::new(file), &config.lines, total_lines)?;
+                    print_lines(
+                        BufReader::new(file),
+                        &config.lines,
+                        total_lines,
+                        config.terminator,
+                        out,
+                    )?;
                 }
             }
         }
     }
-    Ok(())
+    Ok(status)
 }
 
 #[cfg(test)]
@@ -296,13 +364,13 @@ mod tests {
 
     #[test]
     fn test_count_lines_bytes() {
-        let res = count_lines_bytes("tests/inputs/one.txt");
+        let res = count_lines_bytes("tests/inputs/one.txt", b'\n');
 
         assert!(res.is_ok());
 
         assert_eq!(res.unwrap(), (1, 24));
 
-        let res = count_lines_bytes("tests/inputs/ten.txt");
+        let res = count_lines_bytes("tests/inputs/ten.txt", b'\n');
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (10, 49));
     }
@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = tailr::get_args().and_then(tailr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| tailr::get_args().and_then(|parsed| parsed.run(out, tailr::run)),
+        1,
+    ));
 }
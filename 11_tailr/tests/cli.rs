@@ -829,3 +829,13 @@ fn multiple_files_c_plus_3() -> TestResult {
         "tests/expected/all.c+3.out",
     )
 }
+
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "2", "tests/inputs/nul.txt"])
+        .assert()
+        .success()
+        .stdout("two\nlines\0three\0");
+    Ok(())
+}
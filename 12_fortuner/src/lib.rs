@@ -1,17 +1,57 @@
 use std::{
     error::Error,
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
+    fmt,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader, Write},
     path::PathBuf,
 };
 
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    config::{self, Opt, Parsed},
+    generate,
+};
 use rand::{seq::SliceRandom, SeedableRng};
 use regex::{Regex, RegexBuilder};
 use walkdir::WalkDir;
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, FortunerError>;
+
+#[derive(Debug)]
+pub enum FortunerError {
+    InvalidSeed(String),
+    InvalidPattern(String),
+    Source(String, io::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for FortunerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FortunerError::InvalidSeed(val) => write!(f, "\"{}\" not a valid integer", val),
+            FortunerError::InvalidPattern(val) => write!(f, "Invalid --pattern \"{}\"", val),
+            FortunerError::Source(path, e) => write!(f, "{}: {}", path, e),
+            FortunerError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for FortunerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FortunerError::Source(_, e) => Some(e),
+            FortunerError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FortunerError {
+    fn from(e: io::Error) -> Self {
+        FortunerError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
@@ -26,39 +66,44 @@ struct Fortune {
     text: String,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("fortuner")
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const FILES: Opt = Opt::operand("files").multiple();
+    const INSENSITIVE: Opt = Opt::flag("insensitive").short('i').long("insensitive");
+    const PATTERN: Opt = Opt::option("pattern").short('m').long("pattern");
+    const SEED: Opt = Opt::option("seed").short('s').long("seed");
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("fortuner")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust fortune")
         .arg(
-            Arg::with_name("files")
+            FILES
+                .arg()
                 .value_name("FILES")
                 .help("Input files or directories")
-                .multiple(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("insensitive")
-                .short("i")
-                .long("insensitive")
-                .help("Case-insensitive pattern matching"),
-        )
-        .arg(
-            Arg::with_name("pattern")
-                .short("m")
-                .long("pattern")
-                .value_name("PATTERN")
-                .help("Pattern"),
+                .required_unless("print_config"),
         )
-        .arg(
-            Arg::with_name("seed")
-                .short("s")
-                .long("seed")
-                .value_name("SEED")
-                .help("Random seed"),
-        )
-        .get_matches();
+        .arg(INSENSITIVE.arg().help("Case-insensitive pattern matching"))
+        .arg(PATTERN.arg().value_name("PATTERN").help("Pattern"))
+        .arg(SEED.arg().value_name("SEED").help("Random seed"))
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [FILES, INSENSITIVE, PATTERN, SEED];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let seed = matches.value_of("seed").map(parse_u64).transpose()?;
 
@@ -68,23 +113,23 @@ pub fn get_args() -> MyResult<Config> {
             RegexBuilder::new(val)
                 .case_insensitive(matches.is_present("insensitive"))
                 .build()
-                .map_err(|_| format!("Invalid --pattern \"{}\"", val))
+                .map_err(|_| FortunerError::InvalidPattern(val.to_string()))
         })
         .transpose()?;
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         sources: matches.values_of_lossy("files").unwrap(),
         pattern,
         seed,
-    })
+    }))
 }
 
 fn parse_u64(val: &str) -> MyResult<u64> {
     val.parse::<u64>()
-        .map_err(|_| format!("\"{}\" not a valid integer", val).into())
+        .map_err(|_| FortunerError::InvalidSeed(val.to_string()))
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<()> {
     let files = find_files(&config.sources)?;
     let fortunes = read_fortunes(&files)?;
 
@@ -94,21 +139,22 @@ pub fn run(config: Config) -> MyResult<()> {
             .iter()
             .filter(|fortune| pattern.is_match(&fortune.text))
         {
-            // true if changes, a bit complicated if map_or is not being well understood
-            if source.as_ref().map_or(true, |s| s != &fortune.source) {
+            // true if the source changes
+            if source.as_ref() != Some(&fortune.source) {
                 eprintln!("({})\n%", fortune.source);
                 source = Some(fortune.source.clone());
             }
-            println!("{}\n%", fortune.text);
+            writeln!(out, "{}\n%", fortune.text)?;
         }
     } else {
-        println!(
+        writeln!(
+            out,
             "{}",
             pick_fortune(&fortunes, config.seed)
                 // this will guard the unwrap, always assign if empty
                 .or_else(|| Some("No fortunes found".to_string()))
                 .unwrap()
-        );
+        )?;
     }
 
     Ok(())
@@ -118,7 +164,7 @@ fn find_files(paths: &[String]) -> MyResult<Vec<PathBuf>> {
     let mut paths_buf = Vec::new();
     for path in paths {
         match std::fs::metadata(path) {
-            Err(e) => return Err(format!("{}: {}", path, e).into()),
+            Err(e) => return Err(FortunerError::Source(path.to_string(), e)),
             Ok(_) => paths_buf.extend(
                 WalkDir::new(path)
                     .into_iter()
@@ -134,7 +180,7 @@ fn find_files(paths: &[String]) -> MyResult<Vec<PathBuf>> {
     paths_buf.sort();
     paths_buf.dedup();
 
-    return Ok(paths_buf);
+    Ok(paths_buf)
 }
 
 fn read_fortunes(paths: &[PathBuf]) -> MyResult<Vec<Fortune>> {
@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = fortuner::get_args().and_then(fortuner::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| fortuner::get_args().and_then(|parsed| parsed.run(out, fortuner::run)),
+        1,
+    ));
 }
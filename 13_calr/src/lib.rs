@@ -1,7 +1,13 @@
-use std::{error::Error, str::FromStr};
+use std::{error::Error, ffi::OsString, fmt, io, str::FromStr};
 
 use chrono::{Datelike, Local, NaiveDate};
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    color::{self, ColorChoice},
+    config::{self, Opt, Parsed},
+    generate,
+    output::Sink,
+};
 use itertools::izip;
 
 const MONTH_NAMES: [&str; 12] = [
@@ -21,41 +27,96 @@ const MONTH_NAMES: [&str; 12] = [
 
 const LINE_WIDTH: usize = 22;
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+type MyResult<T> = Result<T, CalrError>;
+
+#[derive(Debug)]
+pub enum CalrError {
+    InvalidInteger(String),
+    InvalidMonth(String),
+    YearOutOfRange(String),
+    MonthOutOfRange(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for CalrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalrError::InvalidInteger(val) => write!(f, "Invalid integer \"{}\"", val),
+            CalrError::InvalidMonth(val) => write!(f, "Invalid month \"{}\"", val),
+            CalrError::YearOutOfRange(val) => {
+                write!(f, "year \"{}\" not in the range 1 through 9999", val)
+            }
+            CalrError::MonthOutOfRange(val) => {
+                write!(f, "month \"{}\" not in the range 1 through 12", val)
+            }
+            CalrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CalrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CalrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CalrError {
+    fn from(e: io::Error) -> Self {
+        CalrError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
     month: Option<u32>,
     year: i32,
     today: NaiveDate,
+    color: ColorChoice,
+}
+
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("calr")
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const YEAR: Opt = Opt::operand("year");
+    const MONTH: Opt = Opt::option("month").short('m');
+    const SHOW_YEAR: Opt = Opt::flag("show_year")
+        .short('y')
+        .long("year")
+        .conflicts_with(&["month", "year"]);
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("calr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com.")
         .about("Rust cal")
         // Args
+        .arg(YEAR.arg().value_name("YEAR").help("Year (1-9999)"))
         .arg(
-            Arg::with_name("year")
-                .value_name("YEAR")
-                .help("Year (1-9999)"),
-        )
-        .arg(
-            Arg::with_name("month")
+            MONTH
+                .arg()
                 .value_name("MONTH")
-                .short("m")
                 .help("Month name or number (1-12)"),
         )
-        .arg(
-            Arg::with_name("show_year")
-                .short("y")
-                .long("year")
-                .help("Show whole current year")
-                // .conflicts_with("month")
-                .conflicts_with_all(&["month", "year"]),
-        )
-        .get_matches();
+        .arg(SHOW_YEAR.arg().help("Show whole current year"))
+        .arg(color::arg().help("Highlight today"))
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [YEAR, MONTH, SHOW_YEAR, color::OPT];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
 
     let mut month = matches.value_of("month").map(parse_month).transpose()?;
     let mut year = matches.value_of("year").map(parse_year).transpose()?;
@@ -70,16 +131,17 @@ pub fn get_args() -> MyResult<Config> {
         year = Some(now.year());
     }
 
-    Ok(Config {
+    Ok(Parsed::Run(Config {
         month,
         year: year.unwrap_or(now.year()),
         today: now.naive_local().into(),
-    })
+        color: color::choice(&matches),
+    }))
 }
 
 fn parse_int<T: FromStr>(val: &str) -> MyResult<T> {
     val.parse::<T>()
-        .map_err(|_| format!("Invalid integer \"{}\"", val).into())
+        .map_err(|_| CalrError::InvalidInteger(val.to_string()))
 }
 
 fn parse_year(year: &str) -> MyResult<i32> {
@@ -93,7 +155,7 @@ fn parse_year(year: &str) -> MyResult<i32> {
         if (1..=9999).contains(&num) {
             Ok(num)
         } else {
-            Err(format!("year \"{}\" not in the range 1 through 9999", year).into())
+            Err(CalrError::YearOutOfRange(year.to_string()))
         }
     })
 }
@@ -104,7 +166,7 @@ fn parse_month(month: &str) -> MyResult<u32> {
             if (1..=12).contains(&num) {
                 Ok(num)
             } else {
-                Err(format!("month \"{}\" not in the range 1 through 12", month).into())
+                Err(CalrError::MonthOutOfRange(month.to_string()))
             }
         }
         _ => {
@@ -124,13 +186,20 @@ fn parse_month(month: &str) -> MyResult<u32> {
             if matches.len() == 1 {
                 Ok(matches[0] as u32)
             } else {
-                Err(format!("Invalid month \"{}\"", month).into())
+                Err(CalrError::InvalidMonth(month.to_string()))
             }
         }
     }
 }
 
-fn format_month(year: i32, month: u32, print_year: bool, today: NaiveDate) -> Vec<String> {
+/// `today` is shown in reverse video when `color` is set.
+fn format_month(
+    year: i32,
+    month: u32,
+    print_year: bool,
+    today: NaiveDate,
+    color: bool,
+) -> Vec<String> {
     let mut result = vec![];
 
     let month_name = MONTH_NAMES.get(month as usize - 1).unwrap();
@@ -170,11 +239,15 @@ fn format_month(year: i32, month: u32, print_year: bool, today: NaiveDate) -> Ve
 
     for week in days.chunks(7) {
         let week_formatted: Vec<_> = week
-            .into_iter()
+            .iter()
             .map(|w| match *w {
                 Some(e) => {
                     let mut date_aligned = format!("{:>2}", e);
-                    if year == today.year() && month == today.month() && today.day() as usize == e {
+                    if color
+                        && year == today.year()
+                        && month == today.month()
+                        && today.day() as usize == e
+                    {
                         let reverse = ansi_term::Style::new().reverse();
                         date_aligned = format!("{}", reverse.paint(date_aligned));
                     }
@@ -197,24 +270,24 @@ fn last_day_in_month(year: i32, month: u32) -> NaiveDate {
     next_month_first_day.pred_opt().unwrap()
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+pub fn run(config: Config, out: &mut impl Sink) -> MyResult<()> {
+    let color = color::enabled(config.color, out);
     if let Some(month) = config.month {
-        for line in format_month(config.year, month, true, config.today).iter() {
-            println!("{}", line);
+        for line in format_month(config.year, month, true, config.today, color).iter() {
+            writeln!(out, "{}", line)?;
         }
     } else {
-        println!("{:>32}", config.year);
+        writeln!(out, "{:>32}", config.year)?;
         let months: Vec<_> = (1..13)
-            .into_iter()
-            .map(|month| format_month(config.year, month, false, config.today))
+            .map(|month| format_month(config.year, month, false, config.today, color))
             .collect();
         for (i, chunk) in months.chunks(3).enumerate() {
             if let [m1, m2, m3] = chunk {
                 for (w1, w2, w3) in izip!(m1, m2, m3) {
-                    println!("{}{}{}", w1, w2, w3);
+                    writeln!(out, "{}{}{}", w1, w2, w3)?;
                 }
                 if i < 3 {
-                    println!();
+                    writeln!(out)?;
                 }
             }
         }
@@ -374,7 +447,7 @@ mod tests {
             "23 24 25 26 27 28 29  ",
             "                      ",
         ];
-        assert_eq!(format_month(2020, 2, true, today), leap_february);
+        assert_eq!(format_month(2020, 2, true, today, true), leap_february);
     }
 
     #[test]
@@ -390,7 +463,7 @@ mod tests {
             "24 25 26 27 28 29 30  ",
             "31                    ",
         ];
-        assert_eq!(format_month(2020, 5, false, today), may);
+        assert_eq!(format_month(2020, 5, false, today, true), may);
     }
 
     #[test]
@@ -406,7 +479,26 @@ mod tests {
             "25 26 27 28 29 30     ",
             "                      ",
         ];
-        assert_eq!(format_month(2021, 4, true, today), april_with_highlight);
+        assert_eq!(
+            format_month(2021, 4, true, today, true),
+            april_with_highlight
+        );
+    }
+
+    #[test]
+    fn test_format_month_2021_4_no_color() {
+        let today = NaiveDate::from_ymd_opt(2021, 4, 7).unwrap();
+        let april = vec![
+            "     April 2021       ",
+            "Su Mo Tu We Th Fr Sa  ",
+            "             1  2  3  ",
+            " 4  5  6  7  8  9 10  ",
+            "11 12 13 14 15 16 17  ",
+            "18 19 20 21 22 23 24  ",
+            "25 26 27 28 29 30     ",
+            "                      ",
+        ];
+        assert_eq!(format_month(2021, 4, true, today, false), april);
     }
 
     #[test]
@@ -422,6 +514,6 @@ mod tests {
             "29 30 31              ",
             "                      ",
         ];
-        assert_eq!(format_month(2023, 10, true, today), october);
+        assert_eq!(format_month(2023, 10, true, today, true), october);
     }
 }
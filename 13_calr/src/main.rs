@@ -1,6 +1,8 @@
+use clr_common::output;
+
 fn main() {
-    if let Err(e) = calr::get_args().and_then(calr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| calr::get_args().and_then(|parsed| parsed.run(out, calr::run)),
+        1,
+    ));
 }
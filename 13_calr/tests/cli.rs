@@ -174,6 +174,37 @@ fn default_one_month() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn color() -> TestResult {
+    let today = predicate::str::contains("\u{1b}[7m");
+    let calr = || -> Result<Command, Box<dyn Error>> {
+        let mut cmd = Command::cargo_bin(PRG)?;
+        cmd.env_remove("NO_COLOR").env_remove("CLICOLOR_FORCE");
+        Ok(cmd)
+    };
+
+    // stdout is a pipe here, so the default is plain text
+    calr()?.assert().success().stdout(today.clone().not());
+    calr()?
+        .arg("--color=never")
+        .env("CLICOLOR_FORCE", "1")
+        .assert()
+        .stdout(today.clone().not());
+    calr()?.arg("--color").assert().stdout(today.clone());
+    calr()?.arg("--color=always").assert().stdout(today.clone());
+    calr()?
+        .env("CLICOLOR_FORCE", "1")
+        .assert()
+        .stdout(today.clone());
+    calr()?
+        .env("CLICOLOR_FORCE", "1")
+        .env("NO_COLOR", "1")
+        .assert()
+        .stdout(today.not());
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn test_2_2020_leap_year() -> TestResult {
@@ -207,3 +238,25 @@ fn year() -> TestResult {
     assert_eq!(lines.len(), 37);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn opts_yield_to_operands() -> TestResult {
+    // a default -y gives way to a year or month on the command line
+    let config = tempfile::tempdir()?;
+    let calr = |args: &[&str]| -> Result<Command, Box<dyn Error>> {
+        let mut cmd = Command::cargo_bin(PRG)?;
+        cmd.args(args)
+            .env("XDG_CONFIG_HOME", config.path())
+            .env("CALR_OPTS", "-y");
+        Ok(cmd)
+    };
+    let expected = fs::read_to_string("tests/expected/2020.txt")?;
+    calr(&["2020"])?.assert().success().stdout(expected);
+    let expected = fs::read_to_string("tests/expected/4-2020.txt")?;
+    calr(&["-m", "4", "2020"])?
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
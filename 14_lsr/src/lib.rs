@@ -1,50 +1,105 @@
 use std::os::unix::fs::PermissionsExt;
 use std::os::unix::prelude::MetadataExt;
-use std::{error::Error, fmt::format, path::PathBuf};
-
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use ansi_term::Colour;
 use chrono::{DateTime, Local};
-use clap::{App, Arg};
+use clap::App;
+use clr_common::{
+    color::{self, ColorChoice},
+    config::{self, Opt, Parsed},
+    generate,
+    output::Sink,
+};
 use tabular::{Row, Table};
-use users::{get_user_by_uid, get_group_by_gid};
+use users::{get_group_by_gid, get_user_by_uid};
+
+type MyResult<T> = Result<T, LsrError>;
+
+#[derive(Debug)]
+pub enum LsrError {
+    Path(String, io::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for LsrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LsrError::Path(path, e) => write!(f, "{}: {}", path, e),
+            LsrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for LsrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LsrError::Path(_, e) => Some(e),
+            LsrError::Io(e) => Some(e),
+        }
+    }
+}
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+impl From<io::Error> for LsrError {
+    fn from(e: io::Error) -> Self {
+        LsrError::Io(e)
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
     paths: Vec<String>,
     long: bool,
     show_hidden: bool,
+    color: ColorChoice,
 }
 
-fn find_files(paths: &[String], show_hidden: bool) -> MyResult<Vec<PathBuf>> {
+fn find_files(paths: &[String], show_hidden: bool) -> Vec<MyResult<PathBuf>> {
     let mut result = Vec::new();
 
     for path in paths {
         match std::fs::metadata(path) {
-            Err(e) => eprintln!("{}: {}", path, e),
+            Err(e) => result.push(Err(LsrError::Path(path.to_string(), e))),
             Ok(md) => {
                 if md.is_file() {
                     let pathbuf = PathBuf::from(path);
-                    result.push(pathbuf);
+                    result.push(Ok(pathbuf));
                 } else {
-                    let dirs = std::fs::read_dir(path)?;
+                    let dirs = match std::fs::read_dir(path) {
+                        Err(e) => {
+                            result.push(Err(LsrError::Path(path.to_string(), e)));
+                            continue;
+                        }
+                        Ok(dirs) => dirs,
+                    };
                     for dir in dirs {
-                        let pathbuf = dir?.path();
-                        let is_hidden = pathbuf.file_name().map_or(false, |filename| {
-                            filename.to_string_lossy().starts_with(".")
-                        });
+                        let pathbuf = match dir {
+                            Err(e) => {
+                                result.push(Err(LsrError::Path(path.to_string(), e)));
+                                continue;
+                            }
+                            Ok(dir) => dir.path(),
+                        };
+                        let is_hidden = pathbuf
+                            .file_name()
+                            .is_some_and(|filename| filename.to_string_lossy().starts_with('.'));
 
                         if !show_hidden && is_hidden {
                             continue;
                         }
-                        result.push(pathbuf);
+                        result.push(Ok(pathbuf));
                     }
                 }
             }
         }
     }
 
-    Ok(result)
+    result
 }
 
 fn format_mode(mode: u32) -> String {
@@ -71,7 +126,17 @@ fn format_mode(mode: u32) -> String {
     result
 }
 
-fn format_output(paths: &[PathBuf]) -> MyResult<String> {
+/// The path as listed, in bold blue for directories when `color` is set
+fn display_name(path: &Path, color: bool) -> String {
+    let name = path.display().to_string();
+    if color && path.is_dir() {
+        Colour::Blue.bold().paint(name).to_string()
+    } else {
+        name
+    }
+}
+
+fn format_output(paths: &[PathBuf], color: bool) -> MyResult<String> {
     let fmt = "{:<}{:<}  {:>}  {:<}  {:<}  {:>}  {:<}  {:<}";
     let mut table = Table::new(fmt);
 
@@ -80,10 +145,14 @@ fn format_output(paths: &[PathBuf]) -> MyResult<String> {
 
         let modified: DateTime<Local> = DateTime::from(md.modified()?);
         let uid = md.uid();
-        let user = get_user_by_uid(uid).map(|u| u.name().to_string_lossy().into_owned()).unwrap_or_else(||uid.to_string());
+        let user = get_user_by_uid(uid)
+            .map(|u| u.name().to_string_lossy().into_owned())
+            .unwrap_or_else(|| uid.to_string());
 
         let gid = md.gid();
-        let group = get_group_by_gid(gid).map(|g| g.name().to_string_lossy().into_owned()).unwrap_or_else(||gid.to_string());
+        let group = get_group_by_gid(gid)
+            .map(|g| g.name().to_string_lossy().into_owned())
+            .unwrap_or_else(|| gid.to_string());
 
         table.add_row(
             Row::new()
@@ -94,7 +163,7 @@ fn format_output(paths: &[PathBuf]) -> MyResult<String> {
                 .with_cell(group) //  5 group name
                 .with_cell(md.size().to_string()) //  6 size in bytes
                 .with_cell(modified.format("%b %d %y %H:%M")) //  7 last modification
-                .with_cell(path.display()), // 8 path
+                .with_cell(display_name(path, color)), // 8 path
         );
     }
     Ok(format!("{}", table))
@@ -113,12 +182,14 @@ mod tests {
 
     use super::format_mode;
 
-    use super::find_files;
+    use super::{display_name, find_files, MyResult};
 
     #[test]
     fn test_find_files_nonhidden_1_dir() {
         // find all non-hidden file entries in 1 directory
-        let res = find_files(&["tests/inputs".to_string()], false);
+        let res: MyResult<Vec<_>> = find_files(&["tests/inputs".to_string()], false)
+            .into_iter()
+            .collect();
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
@@ -139,7 +210,9 @@ mod tests {
 
     #[test]
     fn test_find_files_all_entries_1_dir() {
-        let res = find_files(&["tests/inputs".to_string()], true);
+        let res: MyResult<Vec<_>> = find_files(&["tests/inputs".to_string()], true)
+            .into_iter()
+            .collect();
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
@@ -161,7 +234,9 @@ mod tests {
 
     #[test]
     fn test_find_files_hidden_file_is_shown_if_targetted_directly() {
-        let res = find_files(&["tests/inputs/.hidden".to_string()], false);
+        let res: MyResult<Vec<_>> = find_files(&["tests/inputs/.hidden".to_string()], false)
+            .into_iter()
+            .collect();
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
@@ -174,13 +249,15 @@ mod tests {
 
     #[test]
     fn test_find_files_multiple_path() {
-        let res = find_files(
+        let res: MyResult<Vec<_>> = find_files(
             &[
                 "tests/inputs/bustle.txt".to_string(),
                 "tests/inputs/dir".to_string(),
             ],
             false,
-        );
+        )
+        .into_iter()
+        .collect();
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
@@ -194,6 +271,18 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_display_name() {
+        let dir = PathBuf::from("tests/inputs/dir");
+        let file = PathBuf::from("tests/inputs/fox.txt");
+        assert_eq!(display_name(&dir, false), "tests/inputs/dir");
+        assert_eq!(
+            display_name(&dir, true),
+            "\u{1b}[1;34mtests/inputs/dir\u{1b}[0m"
+        );
+        assert_eq!(display_name(&file, true), "tests/inputs/fox.txt");
+    }
+
     #[test]
     fn test_format_mode() {
         assert_eq!(format_mode(0o775), "rwxrwxr-x");
@@ -227,7 +316,7 @@ mod tests {
         let bustle_path = "tests/inputs/bustle.txt";
         let bustle = PathBuf::from(bustle_path);
 
-        let res = format_output(&[bustle]);
+        let res = format_output(&[bustle], false);
         assert!(res.is_ok());
 
         let out = res.unwrap();
@@ -240,10 +329,13 @@ mod tests {
 
     #[test]
     fn test_format_output_two() {
-        let res = format_output(&[
-            PathBuf::from("tests/inputs/dir"),
-            PathBuf::from("tests/inputs/empty.txt"),
-        ]);
+        let res = format_output(
+            &[
+                PathBuf::from("tests/inputs/dir"),
+                PathBuf::from("tests/inputs/empty.txt"),
+            ],
+            false,
+        );
         assert!(res.is_ok());
 
         let out = res.unwrap();
@@ -264,48 +356,74 @@ mod tests {
     }
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("lsr")
+pub fn get_args() -> MyResult<Parsed<Config>> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Parsed<Config>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    const LONG: Opt = Opt::flag("long").short('l').long("long");
+    const ALL: Opt = Opt::flag("all").short('a').long("all");
+    const PATHS: Opt = Opt::operand("paths").multiple();
+
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut app = App::new("lsr")
         .version("0.1.0")
         .author("Denilson <denilson020898@gmail.com>")
         .about("Rust ls")
+        .after_help("EXIT STATUS:\n    0 if every PATH was listed, 1 if any PATH could not be read")
+        .arg(LONG.arg().help("Long listing"))
+        .arg(ALL.arg().help("Show all files"))
         .arg(
-            Arg::with_name("long")
-                .help("Long listing")
-                .short("l")
-                .long("long"),
-        )
-        .arg(
-            Arg::with_name("all")
-                .help("Show all files")
-                .short("a")
-                .long("all"),
-        )
-        .arg(
-            Arg::with_name("paths")
+            PATHS
+                .arg()
                 .help("Files and/or directories")
                 .value_name("PATH")
-                .takes_value(false)
-                .multiple(true)
                 .default_value("."),
         )
-        .get_matches();
-
-    Ok(Config {
+        .arg(color::arg().help("Highlight directories"))
+        .args(&config::args())
+        .arg(generate::arg());
+    generate::exit_if_requested(&mut app, &args);
+
+    let opts = [LONG, ALL, PATHS, color::OPT];
+    let matches = match config::get_matches(app, &opts, args) {
+        Parsed::Run(matches) => matches,
+        Parsed::PrintConfig(settings) => return Ok(Parsed::PrintConfig(settings)),
+    };
+
+    Ok(Parsed::Run(Config {
         paths: matches.values_of_lossy("paths").unwrap(),
         long: matches.is_present("long"),
         show_hidden: matches.is_present("all"),
-    })
+        color: color::choice(&matches),
+    }))
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let paths = find_files(&config.paths, config.show_hidden)?;
+/// Returns the exit status: 1 if any path could not be read.
+pub fn run(config: Config, out: &mut impl Sink) -> MyResult<i32> {
+    let color = color::enabled(config.color, out);
+    let mut status = 0;
+    let mut paths = vec![];
+    for entry in find_files(&config.paths, config.show_hidden) {
+        match entry {
+            Err(e) => {
+                eprintln!("{}", e);
+                status = 1;
+            }
+            Ok(path) => paths.push(path),
+        }
+    }
+
     if config.long {
-        println!("{}", format_output(&paths)?);
+        writeln!(out, "{}", format_output(&paths, color)?)?;
     } else {
         for path in paths {
-            println!("{}", path.display());
+            writeln!(out, "{}", display_name(&path, color))?;
         }
     }
-    Ok(())
+    Ok(status)
 }
@@ -1,8 +1,8 @@
-mod lib;
+use clr_common::output;
 
 fn main() {
-    if let Err(e) = lsr::get_args().and_then(lsr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    std::process::exit(output::execute(
+        |out| lsr::get_args().and_then(|parsed| parsed.run(out, lsr::run)),
+        1,
+    ));
 }
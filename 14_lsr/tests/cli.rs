@@ -35,7 +35,7 @@ fn bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::contains(expected));
     Ok(())
 }
@@ -0,0 +1,241 @@
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{App, AppSettings, Arg};
+use clr_common::{generate, output};
+
+type MyResult<T> = Result<T, ClrError>;
+
+#[derive(Debug)]
+pub enum ClrError {
+    UnknownTool(String),
+    MissingTool,
+    Io(io::Error),
+}
+
+impl fmt::Display for ClrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClrError::UnknownTool(name) => write!(f, "Unknown tool \"{}\" (see --list)", name),
+            ClrError::MissingTool => write!(f, "Must have a TOOL, --list or --install-links"),
+            ClrError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ClrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClrError {
+    fn from(e: io::Error) -> Self {
+        ClrError::Io(e)
+    }
+}
+
+/// Entry point of a single tool, taking the full argument list (including
+/// the program name) and returning the process exit status.
+type Applet = fn(Vec<OsString>) -> i32;
+
+/// `(name, entry point)` for a tool crate exposing `get_args_from()`/`run()`,
+/// optionally with the exit status the tool uses for errors (default 1)
+macro_rules! applet {
+    ($tool:ident) => {
+        applet!($tool, 1)
+    };
+    ($tool:ident, $error_status:expr) => {
+        (stringify!($tool), |args| {
+            output::execute(
+                |out| $tool::get_args_from(args).and_then(|parsed| parsed.run(out, $tool::run)),
+                $error_status,
+            )
+        })
+    };
+}
+
+const APPLETS: &[(&str, Applet)] = &[
+    ("hello", |_| {
+        output::execute(|out| writeln!(out, "Hello, world!!!"), 1)
+    }),
+    ("true", |_| 0),
+    ("false", |_| 1),
+    ("test", hello::test::run),
+    ("[", hello::test::run),
+    applet!(echor),
+    applet!(catr),
+    applet!(headr),
+    applet!(wcr),
+    applet!(uniqr),
+    applet!(findr),
+    applet!(cutr),
+    applet!(grepr, grepr::EXIT_ERROR),
+    applet!(commr),
+    applet!(tailr),
+    applet!(fortuner),
+    applet!(calr),
+    applet!(lsr),
+];
+
+#[derive(Debug)]
+pub enum Config {
+    List,
+    InstallLinks(PathBuf),
+    Run(String, Vec<OsString>),
+}
+
+fn find_applet(name: &str) -> Option<Applet> {
+    APPLETS
+        .iter()
+        .find(|(applet, _)| *applet == name)
+        .map(|(_, func)| *func)
+}
+
+/// The tool name a symlink was invoked as, e.g. "/usr/local/bin/grepr" -> "grepr"
+fn invoked_as(arg0: &OsString) -> Option<String> {
+    Path::new(arg0)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| find_applet(name).is_some())
+}
+
+pub fn get_args() -> MyResult<Config> {
+    get_args_from(std::env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> MyResult<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+
+    // Called through a symlink named after a tool: hand it every argument
+    if let Some(name) = args.first().and_then(invoked_as) {
+        return Ok(Config::Run(name, args));
+    }
+
+    let mut app = App::new("clr")
+        .version("0.1.0")
+        .author("Denilson <denilson020898@gmail.com>")
+        .about("Rust multicall binary for every tool")
+        .usage("clr [FLAGS] [OPTIONS]\n    clr <TOOL> [ARGS]...")
+        .setting(AppSettings::AllowExternalSubcommands)
+        .setting(AppSettings::ArgRequiredElseHelp)
+        .arg(
+            Arg::with_name("list")
+                .short("l")
+                .long("list")
+                .help("List available tools")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("install_links")
+                .long("install-links")
+                .value_name("DIR")
+                .help("Create a symlink to this binary for every tool in DIR")
+                .takes_value(true)
+                .conflicts_with("list"),
+        )
+        .arg(generate::arg());
+    // only clr's own options come before the TOOL
+    let own_args = args
+        .iter()
+        .position(|arg| find_applet(&arg.to_string_lossy()).is_some())
+        .unwrap_or(args.len());
+    generate::exit_if_requested(&mut app, &args[..own_args]);
+
+    let matches = app.get_matches_from(args);
+
+    if matches.is_present("list") {
+        return Ok(Config::List);
+    }
+
+    if let Some(dir) = matches.value_of_os("install_links") {
+        return Ok(Config::InstallLinks(PathBuf::from(dir)));
+    }
+
+    match matches.subcommand() {
+        (name, Some(sub_matches)) => {
+            if find_applet(name).is_none() {
+                return Err(ClrError::UnknownTool(name.to_string()));
+            }
+            let mut tool_args = vec![OsString::from(name)];
+            tool_args.extend(
+                sub_matches
+                    .values_of_os("")
+                    .into_iter()
+                    .flatten()
+                    .map(From::from),
+            );
+            Ok(Config::Run(name.to_string(), tool_args))
+        }
+        _ => Err(ClrError::MissingTool),
+    }
+}
+
+fn install_links(dir: &Path) -> MyResult<i32> {
+    let exe = std::env::current_exe()?;
+    let mut status = 0;
+
+    for (name, _) in APPLETS {
+        let link = dir.join(name);
+        if let Err(e) = std::os::unix::fs::symlink(&exe, &link) {
+            eprintln!("{}: {}", link.display(), e);
+            status = 1;
+        }
+    }
+
+    Ok(status)
+}
+
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<i32> {
+    match config {
+        Config::List => {
+            for (name, _) in APPLETS {
+                writeln!(out, "{}", name)?;
+            }
+            Ok(0)
+        }
+        Config::InstallLinks(dir) => install_links(&dir),
+        // safe to unwrap, the name was looked up in get_args
+        Config::Run(name, args) => Ok(find_applet(&name).unwrap()(args)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{find_applet, invoked_as};
+
+    #[test]
+    fn test_find_applet() {
+        assert!(find_applet("grepr").is_some());
+        assert!(find_applet("true").is_some());
+        assert!(find_applet("grep").is_none());
+        assert!(find_applet("").is_none());
+    }
+
+    #[test]
+    fn test_invoked_as() {
+        assert_eq!(
+            invoked_as(&OsString::from("/usr/local/bin/grepr")),
+            Some("grepr".to_string())
+        );
+        assert_eq!(
+            invoked_as(&OsString::from("catr")),
+            Some("catr".to_string())
+        );
+        assert_eq!(invoked_as(&OsString::from("target/debug/clr")), None);
+    }
+}
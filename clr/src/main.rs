@@ -0,0 +1,8 @@
+use clr_common::output;
+
+fn main() {
+    std::process::exit(output::execute(
+        |out| clr::get_args().and_then(|config| clr::run(config, out)),
+        1,
+    ));
+}
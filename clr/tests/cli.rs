@@ -0,0 +1,151 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::{error::Error, fs, process};
+
+type TestResult = Result<(), Box<dyn Error>>;
+
+const PRG: &str = "clr";
+const FOX: &str = "../09_grepr/tests/inputs/fox.txt";
+
+// --------------------------------------------------
+#[test]
+fn usage() -> TestResult {
+    for flag in &["-h", "--help"] {
+        Command::cargo_bin(PRG)?
+            .arg(flag)
+            .assert()
+            .stdout(predicate::str::contains("USAGE"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_no_args() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("USAGE"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_tool() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("grep")
+        .assert()
+        .failure()
+        .stderr("Unknown tool \"grep\" (see --list)\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn list() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?.arg("--list").assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let tools: Vec<_> = stdout.lines().collect();
+    assert_eq!(tools.len(), 18);
+    assert!(tools.contains(&"echor"));
+    assert!(tools.contains(&"["));
+    assert!(tools.contains(&"lsr"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn subcommand() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["echor", "-n", "Hello", "there"])
+        .assert()
+        .success()
+        .stdout("Hello there");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn subcommand_exit_status() -> TestResult {
+    Command::cargo_bin(PRG)?.arg("true").assert().success();
+    Command::cargo_bin(PRG)?.arg("false").assert().failure();
+    Command::cargo_bin(PRG)?
+        .args(["[", "1", "-lt", "2", "]"])
+        .assert()
+        .success();
+    Command::cargo_bin(PRG)?
+        .args(["test", "1", "-lt"])
+        .assert()
+        .code(2);
+    Command::cargo_bin(PRG)?
+        .args(["grepr", "["])
+        .assert()
+        .failure()
+        .stderr("Invalid pattern \"[\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn generate() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?.arg("--list").assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let tools = stdout
+        .lines()
+        .filter(|tool| !["hello", "true", "false", "test", "["].contains(tool));
+
+    for tool in tools {
+        for target in &["bash", "zsh", "fish"] {
+            Command::cargo_bin(PRG)?
+                .args([tool, "--generate", target])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(tool));
+        }
+        Command::cargo_bin(PRG)?
+            .args([tool, "--generate", "man"])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with(format!(
+                ".TH {} 1",
+                tool.to_uppercase()
+            )));
+    }
+
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "man"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\\-\\-install\\-links"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn install_links() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    Command::cargo_bin(PRG)?
+        .arg("--install-links")
+        .arg(dir.path())
+        .assert()
+        .success();
+
+    let link = dir.path().join("wcr");
+    assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+
+    let output = process::Command::new(&link).args(["-l", FOX]).output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("       1 {}\n", FOX)
+    );
+
+    // Links already exist
+    Command::cargo_bin(PRG)?
+        .arg("--install-links")
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("File exists"));
+    Ok(())
+}
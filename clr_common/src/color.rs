@@ -0,0 +1,117 @@
+//! The `--color=auto|always|never` policy shared by every tool that
+//! highlights its output. `auto` colors only a terminal, and honors the
+//! `NO_COLOR` and `CLICOLOR_FORCE` environment variables.
+
+use std::{env, ffi::OsString};
+
+use clap::{Arg, ArgMatches};
+
+use crate::{config::Opt, output::Sink};
+
+/// Values accepted by `--color`
+pub const WHENS: &[&str] = &["auto", "always", "never"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// `--color[=WHEN]`; a bare `--color` means `always`, like GNU
+pub const OPT: Opt = Opt::option("color").long("color").optional_value();
+
+/// The `OPT` argument
+pub fn arg() -> Arg<'static, 'static> {
+    OPT.arg()
+        .value_name("WHEN")
+        .help("Colorize the output")
+        .possible_values(WHENS)
+}
+
+/// The choice made with `arg()`, `auto` if it was not given
+pub fn choice(matches: &ArgMatches) -> ColorChoice {
+    match matches.value_of("color") {
+        Some("always") => ColorChoice::Always,
+        Some("never") => ColorChoice::Never,
+        Some(_) => ColorChoice::Auto,
+        None if matches.is_present("color") => ColorChoice::Always,
+        None => ColorChoice::Auto,
+    }
+}
+
+/// A variable counts as set when it is present and not empty.
+fn is_set(var: &Option<OsString>) -> bool {
+    var.as_ref().is_some_and(|val| !val.is_empty())
+}
+
+/// Decide whether to color: an explicit `always`/`never` wins, then
+/// `NO_COLOR`, then `CLICOLOR_FORCE` (unless "0"), then the terminal check.
+pub fn resolve(
+    choice: ColorChoice,
+    no_color: Option<OsString>,
+    clicolor_force: Option<OsString>,
+    is_tty: bool,
+) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto if is_set(&no_color) => false,
+        ColorChoice::Auto if is_set(&clicolor_force) => clicolor_force.unwrap() != "0",
+        ColorChoice::Auto => is_tty,
+    }
+}
+
+/// Whether output written to `out` should be colored.
+pub fn enabled(choice: ColorChoice, out: &impl Sink) -> bool {
+    resolve(
+        choice,
+        env::var_os("NO_COLOR"),
+        env::var_os("CLICOLOR_FORCE"),
+        out.is_terminal(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use clap::App;
+
+    use super::{arg, choice, resolve, ColorChoice};
+
+    fn var(val: &str) -> Option<OsString> {
+        Some(OsString::from(val))
+    }
+
+    #[test]
+    fn test_choice() {
+        let parse = |args: &[&str]| {
+            let matches = App::new("calr").arg(arg()).get_matches_from(args);
+            choice(&matches)
+        };
+        assert_eq!(parse(&["calr"]), ColorChoice::Auto);
+        assert_eq!(parse(&["calr", "--color"]), ColorChoice::Always);
+        assert_eq!(parse(&["calr", "--color=auto"]), ColorChoice::Auto);
+        assert_eq!(parse(&["calr", "--color=always"]), ColorChoice::Always);
+        assert_eq!(parse(&["calr", "--color=never"]), ColorChoice::Never);
+    }
+
+    #[test]
+    fn test_resolve_explicit() {
+        assert!(resolve(ColorChoice::Always, var("1"), None, false));
+        assert!(!resolve(ColorChoice::Never, None, var("1"), true));
+    }
+
+    #[test]
+    fn test_resolve_auto() {
+        assert!(resolve(ColorChoice::Auto, None, None, true));
+        assert!(!resolve(ColorChoice::Auto, None, None, false));
+        assert!(!resolve(ColorChoice::Auto, var("1"), None, true));
+        assert!(resolve(ColorChoice::Auto, var(""), None, true));
+        assert!(!resolve(ColorChoice::Auto, var("1"), var("1"), true));
+        assert!(resolve(ColorChoice::Auto, None, var("1"), false));
+        assert!(!resolve(ColorChoice::Auto, None, var("0"), false));
+        assert!(!resolve(ColorChoice::Auto, None, var(""), false));
+    }
+}
@@ -0,0 +1,759 @@
+//! Per-user defaults: `$XDG_CONFIG_HOME/clr/<tool>.toml`, then the
+//! `<TOOL>_OPTS` environment variable, then the command line, each layer
+//! overriding the options set by the one before. `--no-config` skips the
+//! first two, `--print-config` shows the settings the tool would run with.
+//!
+//! The file is TOML keyed by long option name (or the short one when there
+//! is none), at the top level or in a table named after the tool:
+//!
+//! ```toml
+//! # grepr.toml
+//! insensitive = true
+//! color = "always"
+//! ```
+//!
+//! Each tool describes its options once, as `Opt`s, and builds its clap
+//! `Arg`s from them, so that the config file knows the same options.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::{App, Arg, ArgMatches, ErrorKind};
+
+use crate::output::Status;
+
+/// The `--no-config` and `--print-config` flags every tool adds to its `App`
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("no_config")
+            .long("no-config")
+            .help("Ignore the config file and the _OPTS environment variable")
+            .takes_value(false),
+        Arg::with_name("print_config")
+            .long("print-config")
+            .help("Print the effective settings and exit")
+            .takes_value(false),
+    ]
+}
+
+/// A flag, option or operand of a tool, as far as the config file is
+/// concerned. `arg()` starts the clap `Arg` with the same settings.
+#[derive(Debug, Clone, Copy)]
+pub struct Opt {
+    name: &'static str,
+    short: Option<char>,
+    long: Option<&'static str>,
+    takes_value: bool,
+    multiple: bool,
+    optional_value: bool,
+    conflicts: &'static [&'static str],
+}
+
+impl Opt {
+    const fn new(name: &'static str, takes_value: bool) -> Opt {
+        Opt {
+            name,
+            short: None,
+            long: None,
+            takes_value,
+            multiple: false,
+            optional_value: false,
+            conflicts: &[],
+        }
+    }
+
+    /// A switch without a value
+    pub const fn flag(name: &'static str) -> Opt {
+        Opt::new(name, false)
+    }
+
+    /// A switch taking a value
+    pub const fn option(name: &'static str) -> Opt {
+        Opt::new(name, true)
+    }
+
+    /// A positional argument: an `option` without `short` or `long`
+    pub const fn operand(name: &'static str) -> Opt {
+        Opt::new(name, true)
+    }
+
+    pub const fn short(self, short: char) -> Opt {
+        Opt {
+            short: Some(short),
+            ..self
+        }
+    }
+
+    pub const fn long(self, long: &'static str) -> Opt {
+        Opt {
+            long: Some(long),
+            ..self
+        }
+    }
+
+    pub const fn multiple(self) -> Opt {
+        Opt {
+            multiple: true,
+            ..self
+        }
+    }
+
+    /// Only `--long=value` gives a value, which may be left out
+    pub const fn optional_value(self) -> Opt {
+        Opt {
+            optional_value: true,
+            ..self
+        }
+    }
+
+    pub const fn conflicts_with(self, conflicts: &'static [&'static str]) -> Opt {
+        Opt { conflicts, ..self }
+    }
+
+    /// The clap `Arg`, for the tool to add its help and the like to
+    pub fn arg(&self) -> Arg<'static, 'static> {
+        let mut arg = Arg::with_name(self.name)
+            .multiple(self.multiple)
+            .conflicts_with_all(self.conflicts);
+        if let Some(short) = self.short {
+            arg = arg.short(short.to_string());
+        }
+        if let Some(long) = self.long {
+            arg = arg.long(long);
+        }
+        if !self.is_operand() {
+            arg = arg.takes_value(self.takes_value);
+        }
+        if self.optional_value {
+            arg = arg.min_values(0).require_equals(true);
+        }
+        arg
+    }
+
+    fn is_operand(&self) -> bool {
+        self.short.is_none() && self.long.is_none()
+    }
+
+    /// The config file key: the long name, or the short one without it
+    fn key(&self) -> String {
+        match (self.long, self.short) {
+            (Some(long), _) => long.to_string(),
+            (None, Some(short)) => short.to_string(),
+            _ => self.name.to_string(),
+        }
+    }
+
+    /// `--long=value`, which clap never extends with the following words
+    fn with_value(&self, value: &str) -> OsString {
+        match self.long {
+            Some(long) => format!("--{}={}", long, value),
+            None => format!("-{}={}", self.short.unwrap_or_default(), value),
+        }
+        .into()
+    }
+
+    fn bare(&self) -> OsString {
+        match self.long {
+            Some(long) => format!("--{}", long),
+            None => format!("-{}", self.short.unwrap_or_default()),
+        }
+        .into()
+    }
+}
+
+/// One option with its values, or one positional word, of a command line
+struct Unit {
+    name: Option<&'static str>,
+    args: Vec<OsString>,
+}
+
+/// Split `args` into units, rewriting every option in the `--long=value`
+/// form so that a unit means the same wherever it is placed.
+fn units(switches: &[Opt], args: &[OsString]) -> Vec<Unit> {
+    let mut units = vec![];
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+        let (switch, attached) = if text == "--" {
+            units.extend(args.map(|arg| Unit {
+                name: None,
+                args: vec![arg.clone()],
+            }));
+            break;
+        } else if let Some(long) = text.strip_prefix("--") {
+            let (long, value) = match long.split_once('=') {
+                Some((long, value)) => (long, Some(value.to_string())),
+                None => (long, None),
+            };
+            let switch = switches.iter().find(|switch| switch.long == Some(long));
+            (switch, value)
+        } else if text.len() > 1 && text.starts_with('-') {
+            // a bundle of shorts such as -la or -n5 becomes -l -a and -n=5
+            let mut chars = text[1..].char_indices();
+            let mut switch = None;
+            let mut attached = None;
+            while let Some((i, short)) = chars.next() {
+                let found = switches.iter().find(|switch| switch.short == Some(short));
+                match found {
+                    Some(found) if found.takes_value => {
+                        let rest = &text[1 + i + short.len_utf8()..];
+                        let rest = rest.strip_prefix('=').unwrap_or(rest);
+                        if !rest.is_empty() {
+                            attached = Some(rest.to_string());
+                        }
+                        switch = Some(found);
+                        break;
+                    }
+                    Some(found) if chars.as_str().is_empty() => switch = Some(found),
+                    Some(found) => units.push(Unit {
+                        name: Some(found.name),
+                        args: vec![found.bare()],
+                    }),
+                    None => units.push(Unit {
+                        name: None,
+                        args: vec![format!("-{}", short).into()],
+                    }),
+                }
+            }
+            match switch {
+                Some(switch) => (Some(switch), attached),
+                None => continue,
+            }
+        } else {
+            (None, None)
+        };
+
+        let switch = match switch {
+            Some(switch) => switch,
+            None => {
+                units.push(Unit {
+                    name: None,
+                    args: vec![arg.clone()],
+                });
+                continue;
+            }
+        };
+        if !switch.takes_value {
+            units.push(Unit {
+                name: Some(switch.name),
+                args: vec![switch.bare()],
+            });
+            continue;
+        }
+
+        let mut values = vec![];
+        match attached {
+            Some(value) => values.push(value),
+            None if switch.optional_value => {}
+            None => {
+                values.extend(
+                    args.next()
+                        .map(|value| value.to_string_lossy().into_owned()),
+                );
+                while switch.multiple
+                    && args
+                        .peek()
+                        .is_some_and(|value| !value.to_string_lossy().starts_with('-'))
+                {
+                    values.push(args.next().unwrap().to_string_lossy().into_owned());
+                }
+            }
+        }
+        units.push(Unit {
+            name: Some(switch.name),
+            args: if values.is_empty() {
+                vec![switch.bare()]
+            } else {
+                values
+                    .iter()
+                    .map(|value| switch.with_value(value))
+                    .collect()
+            },
+        });
+    }
+    units
+}
+
+/// True if the options named `a` and `b` are the same or cannot be combined
+fn overrides(opts: &[Opt], a: &str, b: &str) -> bool {
+    let conflicts = |name: &str, other: &str| {
+        opts.iter()
+            .any(|opt| opt.name == name && opt.conflicts.contains(&other))
+    };
+    a == b || conflicts(a, b) || conflicts(b, a)
+}
+
+/// Put the `layers` of defaults, lowest first, in front of the command line,
+/// dropping every option that a later layer sets again or that conflicts
+/// with an operand the command line gives.
+fn merge(opts: &[Opt], args: Vec<OsString>, layers: &[Vec<OsString>]) -> Vec<OsString> {
+    let (argv0, rest) = match args.split_first() {
+        Some(split) => split,
+        None => return args,
+    };
+    let end = rest
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(rest.len());
+    let given = units(opts, &rest[..end]);
+    let mut taken: Vec<&str> = given.iter().filter_map(|unit| unit.name).collect();
+    // the operands fill the positionals in order, the words after -- too
+    let is_operand = |arg: &OsString| arg == "-" || !arg.to_string_lossy().starts_with('-');
+    let operands = given
+        .iter()
+        .filter(|unit| unit.name.is_none() && unit.args.iter().all(is_operand))
+        .count()
+        + rest.len().saturating_sub(end + 1);
+    taken.extend(
+        opts.iter()
+            .filter(|opt| opt.is_operand())
+            .take(operands)
+            .map(|opt| opt.name),
+    );
+
+    let mut defaults = vec![];
+    for layer in layers.iter().rev() {
+        let kept: Vec<_> = units(opts, layer)
+            .into_iter()
+            .filter(|unit| match unit.name {
+                Some(name) => !taken.iter().any(|other| overrides(opts, name, other)),
+                None => true,
+            })
+            .collect();
+        taken.extend(kept.iter().filter_map(|unit| unit.name));
+        defaults.splice(0..0, kept.into_iter().flat_map(|unit| unit.args));
+    }
+
+    std::iter::once(argv0.clone())
+        .chain(defaults)
+        .chain(rest.iter().cloned())
+        .collect()
+}
+
+/// The settings of a config file: the top-level keys, and those of the
+/// `[<tool>]` table if there is one, which win.
+fn parse(text: &str, tool: &str) -> Result<Vec<(String, toml::Value)>, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| {
+        let line = e
+            .span()
+            .map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+        format!("line {}: {}", line, e.message().trim().replace('\n', "; "))
+    })?;
+    let mut settings = vec![];
+    let mut own = toml::Table::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(table) if key == tool => own = table,
+            toml::Value::Table(_) => return Err(format!("unknown table [{}]", key)),
+            value => settings.push((key, value)),
+        }
+    }
+    settings.retain(|(key, _)| !own.contains_key(key));
+    settings.extend(own);
+    Ok(settings)
+}
+
+/// A string or a number, as the value of an option
+fn scalar(key: &str, value: toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        _ => Err(format!("\"{}\" must be a string or a number", key)),
+    }
+}
+
+/// The command line options spelled by the settings of a config file
+fn settings_args(
+    opts: &[Opt],
+    settings: Vec<(String, toml::Value)>,
+) -> Result<Vec<OsString>, String> {
+    let mut args = vec![];
+    for (key, value) in settings {
+        let opt = opts
+            .iter()
+            .filter(|opt| !opt.is_operand())
+            .find(|opt| opt.long == Some(&key) || opt.key() == key)
+            .ok_or_else(|| format!("unknown option \"{}\"", key))?;
+        match (opt.takes_value, value) {
+            (false, toml::Value::Boolean(true)) => args.push(opt.bare()),
+            (false, toml::Value::Boolean(false)) => {}
+            (false, _) => return Err(format!("\"{}\" must be true or false", key)),
+            (true, toml::Value::Boolean(true)) if opt.optional_value => args.push(opt.bare()),
+            (true, toml::Value::Boolean(_)) => return Err(format!("\"{}\" needs a value", key)),
+            (true, toml::Value::Array(values)) if opt.multiple => {
+                for value in values {
+                    args.push(opt.with_value(&scalar(&key, value)?));
+                }
+            }
+            (true, toml::Value::Array(_)) => {
+                return Err(format!("\"{}\" takes a single value", key))
+            }
+            (true, value) => args.push(opt.with_value(&scalar(&key, value)?)),
+        }
+    }
+    Ok(args)
+}
+
+/// Split the value of an `_OPTS` variable into words like a shell would,
+/// honoring single and double quotes and backslashes.
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.next());
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// `$XDG_CONFIG_HOME/clr/<tool>.toml`, or `~/.config/clr/<tool>.toml`
+pub fn path(tool: &str) -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("clr").join(format!("{}.toml", tool)))
+}
+
+/// `GREPR_OPTS` for grepr
+pub fn env_var(tool: &str) -> String {
+    format!("{}_OPTS", tool.to_uppercase())
+}
+
+/// Whether `flag` is given on the command line itself
+fn given(args: &[OsString], flag: &str) -> bool {
+    args.iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == flag)
+}
+
+fn error(source: &str, msg: &str) -> clap::Error {
+    clap::Error::with_description(&format!("{}: {}", source, msg), ErrorKind::InvalidValue)
+}
+
+/// Where the defaults came from, for `--print-config`
+struct Source {
+    name: String,
+    args: Vec<OsString>,
+}
+
+/// Read the config file and the environment variable of `tool`.
+fn sources(opts: &[Opt], tool: &str) -> clap::Result<Vec<Source>> {
+    let mut sources = vec![];
+    if let Some(path) = path(tool) {
+        let name = path.display().to_string();
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let settings = parse(&text, tool).map_err(|e| error(&name, &e))?;
+                let args = settings_args(opts, settings).map_err(|e| error(&name, &e))?;
+                sources.push(Source { name, args });
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(error(&name, &e.to_string())),
+        }
+    }
+
+    let var = env_var(tool);
+    if let Some(value) = env::var_os(&var) {
+        let words = split_words(&value.to_string_lossy()).map_err(|e| error(&var, &e))?;
+        sources.push(Source {
+            name: format!("{}={:?}", var, value.to_string_lossy()),
+            args: words.into_iter().map(OsString::from).collect(),
+        });
+    }
+    Ok(sources)
+}
+
+/// A parsed command line: what the tool runs with, or the settings that
+/// `--print-config` asked for
+#[derive(Debug)]
+pub enum Parsed<T> {
+    Run(T),
+    PrintConfig(String),
+}
+
+impl<T> Parsed<T> {
+    /// Hand the tool's config to `run`, or print the settings.
+    pub fn run<W, S, E>(
+        self,
+        out: &mut W,
+        run: impl FnOnce(T, &mut W) -> Result<S, E>,
+    ) -> Result<i32, E>
+    where
+        W: Write,
+        S: Status,
+        E: From<io::Error>,
+    {
+        match self {
+            Parsed::Run(config) => run(config, out).map(Status::status),
+            Parsed::PrintConfig(settings) => {
+                out.write_all(settings.as_bytes())?;
+                Ok(0)
+            }
+        }
+    }
+}
+
+/// `App::get_matches_from_safe` with the user's defaults for the `opts` of
+/// the tool merged in
+pub fn get_matches_safe<'a>(
+    app: App<'a, '_>,
+    opts: &[Opt],
+    args: Vec<OsString>,
+) -> clap::Result<Parsed<ArgMatches<'a>>> {
+    let tool = app.get_name().to_string();
+    let (args, sources) = if given(&args, "--no-config") {
+        (args, vec![])
+    } else {
+        let sources = sources(opts, &tool)?;
+        let layers: Vec<_> = sources.iter().map(|source| source.args.clone()).collect();
+        (merge(opts, args, &layers), sources)
+    };
+
+    let matches = app.get_matches_from_safe(args)?;
+    if matches.is_present("print_config") {
+        return Ok(Parsed::PrintConfig(settings(opts, &matches, &sources)));
+    }
+    Ok(Parsed::Run(matches))
+}
+
+/// `App::get_matches_from` with the user's defaults merged in
+pub fn get_matches<'a>(
+    app: App<'a, '_>,
+    opts: &[Opt],
+    args: Vec<OsString>,
+) -> Parsed<ArgMatches<'a>> {
+    get_matches_safe(app, opts, args).unwrap_or_else(|e| e.exit())
+}
+
+/// `value` as TOML, leaving numbers bare
+fn quote(value: &str) -> String {
+    match value.parse::<i64>() {
+        Ok(num) => toml::Value::Integer(num),
+        Err(_) => toml::Value::String(value.to_string()),
+    }
+    .to_string()
+}
+
+/// The effective settings in the config file format
+fn settings(opts: &[Opt], matches: &ArgMatches, sources: &[Source]) -> String {
+    let mut lines: Vec<_> = sources
+        .iter()
+        .map(|source| format!("# from {}", source.name))
+        .collect();
+    for opt in opts.iter().filter(|opt| !opt.is_operand()) {
+        let key = opt.key();
+        if !opt.takes_value {
+            lines.push(format!("{} = {}", key, matches.is_present(opt.name)));
+            continue;
+        }
+        lines.push(match matches.values_of_lossy(opt.name) {
+            Some(values) if opt.multiple => {
+                let values: Vec<_> = values.iter().map(|value| quote(value)).collect();
+                format!("{} = [{}]", key, values.join(", "))
+            }
+            Some(values) if !values.is_empty() => format!("{} = {}", key, quote(&values[0])),
+            _ if matches.is_present(opt.name) => format!("{} = true", key),
+            _ => format!("# {} is not set", key),
+        });
+    }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{merge, parse, quote, settings_args, split_words, units, Opt};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    const OPTS: &[Opt] = &[
+        Opt::operand("paths").multiple(),
+        Opt::flag("long").short('l').long("long"),
+        Opt::flag("all").short('a').long("all"),
+        Opt::option("width").short('w').long("width"),
+        Opt::option("names").short('n').long("name").multiple(),
+        Opt::flag("one").short('1').conflicts_with(&["long"]),
+        Opt::flag("home").short('H').conflicts_with(&["paths"]),
+    ];
+
+    #[test]
+    fn test_units() {
+        let split: Vec<_> = units(OPTS, &args(&["-la", "-w5", "-n", "a", "b", "-1", "x"]))
+            .into_iter()
+            .map(|unit| (unit.name, unit.args))
+            .collect();
+        assert_eq!(
+            split,
+            vec![
+                (Some("long"), args(&["--long"])),
+                (Some("all"), args(&["--all"])),
+                (Some("width"), args(&["--width=5"])),
+                (Some("names"), args(&["--name=a", "--name=b"])),
+                (Some("one"), args(&["-1"])),
+                (None, args(&["x"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let file = args(&["--long", "--width=80"]);
+        let env = args(&["-w", "100", "-a"]);
+
+        assert_eq!(
+            merge(OPTS, args(&["lsr", "dir"]), &[file.clone(), env.clone()]),
+            args(&["lsr", "--long", "--width=100", "--all", "dir"])
+        );
+        // the command line wins, including over conflicting options
+        assert_eq!(
+            merge(OPTS, args(&["lsr", "-1", "--width", "7"]), &[file, env]),
+            args(&["lsr", "--all", "-1", "--width", "7"])
+        );
+        // options after -- are operands
+        assert_eq!(
+            merge(OPTS, args(&["lsr", "--", "-a"]), &[args(&["-a"])]),
+            args(&["lsr", "--all", "--", "-a"])
+        );
+        // and a default that conflicts with an operand goes
+        let home = [args(&["-H", "-a"])];
+        assert_eq!(
+            merge(OPTS, args(&["lsr", "-l", "dir"]), &home),
+            args(&["lsr", "--all", "-l", "dir"])
+        );
+        assert_eq!(
+            merge(OPTS, args(&["lsr", "--", "dir"]), &home),
+            args(&["lsr", "--all", "--", "dir"])
+        );
+        assert_eq!(
+            merge(OPTS, args(&["lsr", "-l"]), &home),
+            args(&["lsr", "-H", "--all", "-l"])
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "# defaults\nlong = true\nall = false # hidden files\n\
+                    width = 80\nname = [\n  \"a\", # first\n  'b\\c',\n]\n\
+                    \"1\" = \"x#y\"\n\n[lsr]\nwidth = 100\n";
+        assert_eq!(
+            parse(text, "lsr").unwrap(),
+            vec![
+                ("1".to_string(), toml::Value::String("x#y".to_string())),
+                ("all".to_string(), toml::Value::Boolean(false)),
+                ("long".to_string(), toml::Value::Boolean(true)),
+                (
+                    "name".to_string(),
+                    toml::Value::Array(vec![
+                        toml::Value::String("a".to_string()),
+                        toml::Value::String("b\\c".to_string()),
+                    ])
+                ),
+                ("width".to_string(), toml::Value::Integer(100)),
+            ]
+        );
+        assert_eq!(
+            parse("[grepr]\ncount = true", "lsr").unwrap_err(),
+            "unknown table [grepr]"
+        );
+        assert!(parse("\nlong", "lsr").unwrap_err().starts_with("line 2: "));
+        assert!(parse("width = wide", "lsr")
+            .unwrap_err()
+            .starts_with("line 1: "));
+        assert!(parse("width = \"8", "lsr")
+            .unwrap_err()
+            .starts_with("line 1: "));
+    }
+
+    #[test]
+    fn test_settings_args() {
+        let settings = parse(
+            "long = true\nall = false\nwidth = 80\nname = [\"a\", 2]\n1 = true",
+            "lsr",
+        )
+        .unwrap();
+        assert_eq!(
+            settings_args(OPTS, settings).unwrap(),
+            args(&["-1", "--long", "--name=a", "--name=2", "--width=80"])
+        );
+
+        let fails = |text: &str| settings_args(OPTS, parse(text, "lsr").unwrap()).unwrap_err();
+        assert_eq!(fails("size = 1"), "unknown option \"size\"");
+        assert_eq!(fails("paths = \"dir\""), "unknown option \"paths\"");
+        assert_eq!(fails("long = \"yes\""), "\"long\" must be true or false");
+        assert_eq!(fails("width = true"), "\"width\" needs a value");
+        assert_eq!(fails("width = [1, 2]"), "\"width\" takes a single value");
+        assert_eq!(
+            fails("name = [{}]"),
+            "\"name\" must be a string or a number"
+        );
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words("  -i  --color=always ").unwrap(),
+            ["-i", "--color=always"]
+        );
+        assert_eq!(
+            split_words(r#"-e 'a b' "c \"d\"" e\ f"#).unwrap(),
+            ["-e", "a b", "c \"d\"", "e f"]
+        );
+        assert_eq!(split_words("''").unwrap(), [""]);
+        assert!(split_words("'open").is_err());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("10"), "10");
+        assert_eq!(quote("-5"), "-5");
+        assert_eq!(quote("a\"b\\\t"), "'a\"b\\\t'");
+        assert_eq!(quote("a\0b"), "\"a\\u0000b\"");
+    }
+}
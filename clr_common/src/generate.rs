@@ -0,0 +1,364 @@
+//! The hidden `--generate {bash,zsh,fish,man}` mode of every tool: shell
+//! completions come straight from clap, man pages are rendered as roff from
+//! the same `App` definition.
+
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+};
+
+use clap::{App, Arg, ArgSettings, ErrorKind, Shell};
+
+use crate::output;
+
+/// Values accepted by `--generate`
+pub const TARGETS: &[&str] = &["bash", "zsh", "fish", "man"];
+
+/// The hidden `--generate` argument every tool adds to its `App`
+pub fn arg() -> Arg<'static, 'static> {
+    Arg::with_name("generate")
+        .long("generate")
+        .value_name("TARGET")
+        .help("Print a shell completion script or a man page")
+        .possible_values(TARGETS)
+        .takes_value(true)
+        .hidden(true)
+}
+
+/// The `--generate` value in `args`, found before clap parses them so that
+/// required arguments such as a PATTERN can be left out. It must be the
+/// first argument, so that an operand spelled like it is left alone.
+fn requested(args: &[OsString]) -> Option<String> {
+    let arg = args.get(1)?.to_string_lossy();
+    if arg == "--generate" {
+        args.get(2)
+            .map(|target| target.to_string_lossy().into_owned())
+    } else {
+        arg.strip_prefix("--generate=").map(String::from)
+    }
+}
+
+/// If `args` asks for `--generate TARGET`, print it for `app` and exit.
+pub fn exit_if_requested(app: &mut App, args: &[OsString]) {
+    if let Some(target) = requested(args) {
+        if !TARGETS.contains(&target.as_str()) {
+            clap::Error::with_description(
+                &format!(
+                    "'{}' isn't a valid value for '--generate <TARGET>'\n\t\
+                     [possible values: {}]",
+                    target,
+                    TARGETS.join(", ")
+                ),
+                ErrorKind::InvalidValue,
+            )
+            .exit();
+        }
+        std::process::exit(output::execute(|out| write(app, &target, out), 1));
+    }
+}
+
+/// Write the completion script or man page named by `target` for `app`.
+pub fn write(app: &mut App, target: &str, out: &mut impl Write) -> io::Result<()> {
+    let name = app.p.meta.name.clone();
+    match target {
+        "bash" => app.gen_completions_to(name, Shell::Bash, out),
+        "zsh" => app.gen_completions_to(name, Shell::Zsh, out),
+        "fish" => app.gen_completions_to(name, Shell::Fish, out),
+        _ => return write_man(app, out),
+    }
+    Ok(())
+}
+
+/// Escape text for roff: backslashes, and dots or quotes starting a line
+fn roff(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .lines()
+        .map(|line| match line.chars().next() {
+            Some('.') | Some('\'') => format!("\\&{}", line),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape an option name so man shows real hyphens
+fn roff_flag(flag: &str) -> String {
+    flag.replace('-', "\\-")
+}
+
+/// `\fB-s\fR, \fB--long\fR` for a flag or option
+fn switches(short: Option<char>, long: Option<&str>) -> String {
+    short
+        .map(|s| format!("-{}", s))
+        .into_iter()
+        .chain(long.map(|l| format!("--{}", l)))
+        .map(|switch| format!("\\fB{}\\fR", roff_flag(&switch)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The help lines shown for an argument, with its possible and default values
+fn describe(
+    help: Option<&str>,
+    possible_vals: Option<&Vec<&str>>,
+    default_val: Option<&std::ffi::OsStr>,
+) -> String {
+    let mut lines = vec![help.unwrap_or("").to_string()];
+    if let Some(vals) = possible_vals {
+        lines.push(format!("[possible values: {}]", vals.join(", ")));
+    }
+    if let Some(val) = default_val {
+        lines.push(format!("[default: {}]", val.to_string_lossy()));
+    }
+    lines.retain(|line| !line.is_empty());
+    roff(&lines.join("\n")).replace('\n', "\n.br\n")
+}
+
+/// The "USAGE:" lines clap would print in `--help`
+fn usage(app: &App) -> io::Result<Vec<String>> {
+    let mut help = vec![];
+    app.write_help(&mut help)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(String::from_utf8_lossy(&help)
+        .lines()
+        .skip_while(|line| line.trim() != "USAGE:")
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| line.trim().to_string())
+        .collect())
+}
+
+fn write_man(app: &App, out: &mut impl Write) -> io::Result<()> {
+    let meta = &app.p.meta;
+    let version = meta.version.unwrap_or("");
+    writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"",
+        meta.name.to_uppercase(),
+        meta.name,
+        version
+    )?;
+
+    writeln!(out, ".SH NAME")?;
+    match meta.about {
+        Some(about) => writeln!(out, "{} \\- {}", meta.name, roff(about))?,
+        None => writeln!(out, "{}", meta.name)?,
+    }
+
+    writeln!(out, ".SH SYNOPSIS")?;
+    for line in usage(app)? {
+        writeln!(out, "{}\n.br", roff(&line))?;
+    }
+
+    let mut switched: Vec<_> = app
+        .p
+        .flags
+        .iter()
+        .filter(|flag| !flag.b.is_set(ArgSettings::Hidden))
+        .map(|flag| {
+            (
+                flag.s.unified_ord,
+                switches(flag.s.short, flag.s.long),
+                describe(flag.b.help, None, None),
+            )
+        })
+        .chain(
+            app.p
+                .opts
+                .iter()
+                .filter(|opt| !opt.b.is_set(ArgSettings::Hidden))
+                .map(|opt| {
+                    let value = opt
+                        .v
+                        .val_names
+                        .as_ref()
+                        .and_then(|names| names.values().next().copied())
+                        .unwrap_or(opt.b.name);
+                    let multiple = if opt.b.is_set(ArgSettings::Multiple) {
+                        "..."
+                    } else {
+                        ""
+                    };
+                    (
+                        opt.s.unified_ord,
+                        format!(
+                            "{} \\fI{}\\fR{}",
+                            switches(opt.s.short, opt.s.long),
+                            value,
+                            multiple
+                        ),
+                        describe(opt.b.help, opt.v.possible_vals.as_ref(), opt.v.default_val),
+                    )
+                }),
+        )
+        .collect();
+    switched.sort_by_key(|(ord, _, _)| *ord);
+
+    writeln!(out, ".SH OPTIONS")?;
+    for (_, term, description) in switched {
+        writeln!(out, ".TP\n{}\n{}", term, description)?;
+    }
+    writeln!(
+        out,
+        ".TP\n{}\nPrints help information",
+        switches(Some('h'), Some("help"))
+    )?;
+    writeln!(
+        out,
+        ".TP\n{}\nPrints version information",
+        switches(Some('V'), Some("version"))
+    )?;
+
+    let positionals: Vec<_> = app
+        .p
+        .positionals
+        .values()
+        .filter(|pos| !pos.b.is_set(ArgSettings::Hidden))
+        .collect();
+    if !positionals.is_empty() {
+        writeln!(out, ".SH ARGUMENTS")?;
+        for pos in positionals {
+            let value = pos
+                .v
+                .val_names
+                .as_ref()
+                .and_then(|names| names.values().next().copied())
+                .unwrap_or(pos.b.name);
+            let multiple = if pos.b.is_set(ArgSettings::Multiple) {
+                "..."
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                ".TP\n\\fI{}\\fR{}\n{}",
+                value,
+                multiple,
+                describe(pos.b.help, pos.v.possible_vals.as_ref(), pos.v.default_val)
+            )?;
+        }
+    }
+
+    // after_help blocks such as "EXIT STATUS:\n    0 if ..." become sections
+    if let Some(more_help) = meta.more_help {
+        for block in more_help.split("\n\n") {
+            let mut lines = block.lines();
+            if let Some(heading) = lines.next() {
+                match heading.strip_suffix(':') {
+                    Some(title) if title.to_uppercase() == title => {
+                        writeln!(out, ".SH {}", title)?;
+                    }
+                    _ => writeln!(out, ".PP\n{}", roff(heading.trim()))?,
+                }
+            }
+            for line in lines {
+                writeln!(out, "{}\n.br", roff(line.trim()))?;
+            }
+        }
+    }
+
+    if let Some(author) = meta.author {
+        writeln!(out, ".SH AUTHOR\n{}", roff(author))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use clap::{App, Arg};
+
+    use super::{requested, roff, write};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn app() -> App<'static, 'static> {
+        App::new("findr")
+            .version("0.1.0")
+            .about("Rust find")
+            .after_help("EXIT STATUS:\n    0 if every PATH was searched")
+            .arg(
+                Arg::with_name("types")
+                    .short("t")
+                    .long("type")
+                    .value_name("TYPE")
+                    .help("Entry type")
+                    .possible_values(&["f", "d", "l"])
+                    .takes_value(true)
+                    .multiple(true),
+            )
+            .arg(
+                Arg::with_name("paths")
+                    .value_name("PATH")
+                    .help("Search paths")
+                    .default_value(".")
+                    .multiple(true),
+            )
+            .arg(super::arg())
+    }
+
+    fn generated(target: &str) -> String {
+        let mut out = vec![];
+        write(&mut app(), target, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_requested() {
+        assert_eq!(requested(&args(&["findr"])), None);
+        assert_eq!(
+            requested(&args(&["findr", "--generate", "bash"])),
+            Some("bash".to_string())
+        );
+        assert_eq!(
+            requested(&args(&["findr", "--generate=man"])),
+            Some("man".to_string())
+        );
+        // anywhere else it is an operand, or an option's value
+        assert_eq!(
+            requested(&args(&["echor", "--generate=bash"])),
+            Some("bash".to_string())
+        );
+        assert_eq!(requested(&args(&["echor", "hi", "--generate=bash"])), None);
+        assert_eq!(requested(&args(&["grepr", "-e", "--generate", "x"])), None);
+        assert_eq!(
+            requested(&args(&["findr", "-t", "f", "--generate=man"])),
+            None
+        );
+        assert_eq!(requested(&args(&["findr", "--generate"])), None);
+        assert_eq!(requested(&args(&["grepr", "--", "--generate"])), None);
+        // only options of the tool itself are looked at
+        assert_eq!(requested(&args(&["--generate", "bash"])), None);
+    }
+
+    #[test]
+    fn test_roff() {
+        assert_eq!(roff("plain text"), "plain text");
+        assert_eq!(roff("a\\b"), "a\\eb");
+        assert_eq!(roff(".start\n'quote"), "\\&.start\n\\&'quote");
+    }
+
+    #[test]
+    fn test_completions() {
+        assert!(generated("bash").contains("f d l"));
+        assert!(generated("zsh").contains("(f d l)"));
+        assert!(generated("fish").contains("-r -f -a \"f d l\""));
+    }
+
+    #[test]
+    fn test_man() {
+        let man = generated("man");
+        assert!(man.starts_with(".TH FINDR 1 \"\" \"findr 0.1.0\" \"User Commands\"\n"));
+        assert!(man.contains(".SH NAME\nfindr \\- Rust find\n"));
+        assert!(man.contains(
+            ".TP\n\\fB\\-t\\fR, \\fB\\-\\-type\\fR \\fITYPE\\fR...\n\
+             Entry type\n.br\n[possible values: f, d, l]\n"
+        ));
+        assert!(man.contains(".TP\n\\fIPATH\\fR...\nSearch paths\n.br\n[default: .]\n"));
+        assert!(man.contains(".SH EXIT STATUS\n0 if every PATH was searched\n"));
+        assert!(!man.contains("generate"));
+    }
+}
@@ -0,0 +1,389 @@
+//! Opening input operands: "-" is stdin, anything else is a file, and
+//! gzip, bzip2, xz or zstd data is decompressed on the fly.
+
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor, Read},
+};
+#[cfg(unix)]
+use std::{
+    mem::ManuallyDrop,
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+/// Enough bytes to recognize every supported format (xz has the longest magic)
+const MAGIC_LEN: usize = 6;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+/// The leading bytes of each compressed format
+const MAGICS: &[(&[u8], Compression)] = &[
+    (&[0x1f, 0x8b], Compression::Gzip),
+    (b"BZh", Compression::Bzip2),
+    (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], Compression::Xz),
+    (&[0x28, 0xb5, 0x2f, 0xfd], Compression::Zstd),
+];
+
+impl Compression {
+    /// Recognize a compressed stream from its first bytes
+    pub fn detect(magic: &[u8]) -> Compression {
+        MAGICS
+            .iter()
+            .find(|(bytes, _)| magic.starts_with(bytes))
+            .map_or(Compression::None, |&(_, compression)| compression)
+    }
+}
+
+/// An operand that failed to open or read, displayed as "FILE: error"
+#[derive(Debug)]
+pub struct InputError {
+    filename: String,
+    source: io::Error,
+}
+
+impl InputError {
+    pub fn new(filename: &str, source: io::Error) -> Self {
+        InputError {
+            filename: filename.to_string(),
+            source,
+        }
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.source.kind()
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.filename, self.source)
+    }
+}
+
+impl Error for InputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// True if `err` came from reading an operand, and so names it, rather than
+/// from writing the output. Tools report such an error and go on to the
+/// next operand.
+pub fn is_read_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|e| e.is::<InputError>())
+}
+
+/// A decoded input operand; read errors carry the operand name.
+pub struct Input {
+    filename: String,
+    reader: Box<dyn BufRead>,
+}
+
+impl Input {
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+}
+
+fn annotate(filename: &str, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), InputError::new(filename, e))
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader
+            .read(buf)
+            .map_err(|e| annotate(&self.filename, e))
+    }
+}
+
+impl BufRead for Input {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader
+            .fill_buf()
+            .map_err(|e| annotate(&self.filename, e))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// stdin, read straight from its file descriptor on Unix so that no bytes
+/// are left behind in a buffer when a tool hands the descriptor to the
+/// kernel
+#[cfg(unix)]
+type Stdin = ManuallyDrop<File>;
+#[cfg(not(unix))]
+type Stdin = io::Stdin;
+
+#[cfg(unix)]
+fn stdin() -> Stdin {
+    // SAFETY: descriptor 0 stays open for the whole process, and
+    // `ManuallyDrop` keeps the `File` from ever closing it
+    ManuallyDrop::new(unsafe { File::from_raw_fd(0) })
+}
+
+#[cfg(not(unix))]
+fn stdin() -> Stdin {
+    io::stdin()
+}
+
+/// The bytes of an operand as they are stored, before any decoding
+pub enum Source {
+    Stdin(Stdin),
+    File(File),
+}
+
+impl Read for Source {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::Stdin(stdin) => stdin.read(buf),
+            Source::File(file) => file.read(buf),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for Source {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Source::Stdin(stdin) => stdin.as_raw_fd(),
+            Source::File(file) => file.as_raw_fd(),
+        }
+    }
+}
+
+fn source(filename: &str) -> Result<Source, InputError> {
+    match filename {
+        "-" => Ok(Source::Stdin(stdin())),
+        _ => File::open(filename)
+            .map(Source::File)
+            .map_err(|e| InputError::new(filename, e)),
+    }
+}
+
+/// An operand opened with `open_raw()`
+pub enum Opened {
+    /// Not compressed: the bytes that were read to find that out, and the
+    /// source positioned right after them
+    Plain(Vec<u8>, Source),
+    Decoded(Input),
+}
+
+/// Open "-" (stdin) or a file, transparently decompressing it.
+pub fn open(filename: &str) -> Result<Input, InputError> {
+    Ok(Input {
+        filename: filename.to_string(),
+        reader: decode(source(filename)?).map_err(|e| InputError::new(filename, e))?,
+    })
+}
+
+/// Like `open()`, but an operand that is not compressed comes back as is,
+/// for tools that copy bytes without looking at them.
+pub fn open_raw(filename: &str) -> Result<Opened, InputError> {
+    let mut source = source(filename)?;
+    let magic = sniff(&mut source).map_err(|e| InputError::new(filename, e))?;
+    Ok(match Compression::detect(&magic) {
+        Compression::None => Opened::Plain(magic, source),
+        compression => Opened::Decoded(Input {
+            filename: filename.to_string(),
+            reader: decoder(compression, Cursor::new(magic).chain(source))
+                .map_err(|e| InputError::new(filename, e))?,
+        }),
+    })
+}
+
+/// The first bytes of `source`, enough to tell its `Compression`. Reading
+/// stops as soon as they cannot begin a longer magic, so that a line typed
+/// on a terminal is not held back waiting for more.
+fn sniff<R: Read>(source: &mut R) -> io::Result<Vec<u8>> {
+    let mut magic = [0; MAGIC_LEN];
+    let mut len = 0;
+    let unsure = |seen: &[u8]| {
+        MAGICS
+            .iter()
+            .any(|(bytes, _)| bytes.len() > seen.len() && bytes.starts_with(seen))
+    };
+    while len < MAGIC_LEN && unsure(&magic[..len]) {
+        match source.read(&mut magic[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(magic[..len].to_vec())
+}
+
+fn decoder<R: Read + 'static>(compression: Compression, stream: R) -> io::Result<Box<dyn BufRead>> {
+    Ok(match compression {
+        Compression::None => Box::new(BufReader::new(stream)),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(stream))),
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(stream))),
+        Compression::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(stream))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::new(stream)?)),
+    })
+}
+
+/// Sniff the first bytes of `source` and wrap it in the matching decoder.
+pub fn decode<R: Read + 'static>(mut source: R) -> io::Result<Box<dyn BufRead>> {
+    let magic = sniff(&mut source)?;
+    let compression = Compression::detect(&magic);
+    // put the sniffed bytes back in front of the rest of the stream
+    decoder(compression, Cursor::new(magic).chain(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::{decode, is_read_error, open, open_raw, sniff, Compression, InputError, Opened};
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog.\n";
+
+    fn decoded(data: Vec<u8>) -> String {
+        let mut out = String::new();
+        decode(Cursor::new(data))
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::detect(b""), Compression::None);
+        assert_eq!(Compression::detect(b"\x1f"), Compression::None);
+        assert_eq!(Compression::detect(b"\x1f\x8b\x08"), Compression::Gzip);
+        assert_eq!(Compression::detect(b"BZh91AY"), Compression::Bzip2);
+        assert_eq!(Compression::detect(b"BZ"), Compression::None);
+        assert_eq!(Compression::detect(b"\xfd7zXZ\x00"), Compression::Xz);
+        assert_eq!(Compression::detect(b"\x28\xb5\x2f\xfd"), Compression::Zstd);
+        assert_eq!(Compression::detect(TEXT.as_bytes()), Compression::None);
+    }
+
+    /// A terminal that has one line typed so far
+    struct Typed(Option<&'static [u8]>);
+
+    impl Read for Typed {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let line = self.0.take().ok_or(io::ErrorKind::WouldBlock)?;
+            buf[..line.len()].copy_from_slice(line);
+            Ok(line.len())
+        }
+    }
+
+    #[test]
+    fn test_sniff_short_read() {
+        assert_eq!(sniff(&mut Typed(Some(b"hi\n"))).unwrap(), b"hi\n");
+        assert_eq!(sniff(&mut Typed(Some(b"\x1f\x8b"))).unwrap(), b"\x1f\x8b");
+        // the start of a magic is worth waiting for
+        assert!(sniff(&mut Typed(Some(b"BZ"))).is_err());
+    }
+
+    #[test]
+    fn test_is_read_error() {
+        let named = io::Error::other(InputError::new("x.txt", io::Error::other("gone")));
+        assert!(is_read_error(&named));
+        assert!(!is_read_error(&io::Error::from(io::ErrorKind::BrokenPipe)));
+        assert!(!is_read_error(&io::Error::other("plain")));
+    }
+
+    #[test]
+    fn test_decode_plain() {
+        assert_eq!(decoded(TEXT.as_bytes().to_vec()), TEXT);
+        assert_eq!(decoded(b"a".to_vec()), "a");
+        assert_eq!(decoded(vec![]), "");
+    }
+
+    #[test]
+    fn test_decode_gzip() {
+        let mut enc = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        let mut data = enc.finish().unwrap();
+
+        // concatenated members are read as one stream, like zcat
+        data.extend(data.clone());
+        assert_eq!(decoded(data), TEXT.repeat(2));
+    }
+
+    #[test]
+    fn test_decode_bzip2() {
+        let mut enc = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        assert_eq!(decoded(enc.finish().unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_decode_xz() {
+        let mut enc = xz2::write::XzEncoder::new(vec![], 6);
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        assert_eq!(decoded(enc.finish().unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_decode_zstd() {
+        let data = zstd::encode_all(TEXT.as_bytes(), 0).unwrap();
+        assert_eq!(decoded(data), TEXT);
+    }
+
+    #[test]
+    fn test_decode_corrupt_stream() {
+        let mut out = String::new();
+        let res = decode(Cursor::new(b"\x1f\x8bnot gzip".to_vec()))
+            .unwrap()
+            .read_to_string(&mut out);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_open_file() {
+        let res = open("Cargo.toml");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().filename(), "Cargo.toml");
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        let res = open("does/not/exist");
+        assert!(res.is_err());
+        assert_eq!(
+            res.err().unwrap().to_string(),
+            "does/not/exist: No such file or directory (os error 2)"
+        );
+    }
+
+    #[test]
+    fn test_open_raw() {
+        match open_raw("Cargo.toml").unwrap() {
+            Opened::Plain(magic, mut source) => {
+                let mut rest = String::new();
+                source.read_to_string(&mut rest).unwrap();
+                assert_eq!(
+                    String::from_utf8(magic).unwrap() + &rest,
+                    std::fs::read_to_string("Cargo.toml").unwrap()
+                );
+            }
+            Opened::Decoded(_) => panic!("Cargo.toml is not compressed"),
+        }
+        assert!(open_raw("does/not/exist").is_err());
+    }
+}
@@ -0,0 +1,8 @@
+//! Pieces shared by every tool in the workspace.
+
+pub mod color;
+pub mod config;
+pub mod generate;
+pub mod input;
+pub mod output;
+pub mod record;
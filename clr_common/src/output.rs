@@ -0,0 +1,160 @@
+//! Writing results: every tool's `run()` takes an `impl Write` sink, and the
+//! binaries hand it a locked, buffered stdout through `execute()`.
+
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::{
+    error::Error,
+    io::{self, BufWriter, IsTerminal, StdoutLock, Write},
+};
+
+/// What a shell reports for a process killed by SIGPIPE (128 + 13)
+pub const EXIT_BROKEN_PIPE: i32 = 141;
+
+/// What a tool's `run()` returns on success: nothing, or its own exit status
+pub trait Status {
+    fn status(self) -> i32;
+}
+
+impl Status for () {
+    fn status(self) -> i32 {
+        0
+    }
+}
+
+impl Status for i32 {
+    fn status(self) -> i32 {
+        self
+    }
+}
+
+/// A `run()` sink that may let the kernel write into it directly
+pub trait Sink: Write {
+    /// Flush what is buffered and return the file descriptor behind the
+    /// sink, if there is one.
+    #[cfg(unix)]
+    fn raw_fd(&mut self) -> io::Result<Option<RawFd>> {
+        Ok(None)
+    }
+
+    /// True if what is written lands on a terminal
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl Sink for Vec<u8> {}
+
+impl Sink for BufWriter<StdoutLock<'static>> {
+    #[cfg(unix)]
+    fn raw_fd(&mut self) -> io::Result<Option<RawFd>> {
+        self.flush()?;
+        Ok(Some(self.get_ref().as_raw_fd()))
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.get_ref().is_terminal()
+    }
+}
+
+/// Locked, buffered stdout
+pub fn stdout() -> BufWriter<StdoutLock<'static>> {
+    BufWriter::new(io::stdout().lock())
+}
+
+/// True if `err`, or any error it wraps, is a write to a closed pipe.
+pub fn is_broken_pipe(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(e) = source {
+        if let Some(io_err) = e.downcast_ref::<io::Error>() {
+            if io_err.kind() == io::ErrorKind::BrokenPipe {
+                return true;
+            }
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// Turn the outcome of a tool into its exit status: print the error and
+/// return `error_status`, except for a closed pipe, which exits quietly
+/// like the C tools do.
+pub fn report<S, E>(result: Result<S, E>, error_status: i32) -> i32
+where
+    S: Status,
+    E: Error + 'static,
+{
+    match result {
+        Ok(status) => status.status(),
+        Err(e) if is_broken_pipe(&e) => EXIT_BROKEN_PIPE,
+        Err(e) => {
+            eprintln!("{}", e);
+            error_status
+        }
+    }
+}
+
+/// Run `body` against a locked, buffered stdout, flush it and return the
+/// exit status for `std::process::exit()`.
+pub fn execute<S, E, F>(body: F, error_status: i32) -> i32
+where
+    S: Status,
+    E: Error + From<io::Error> + 'static,
+    F: FnOnce(&mut BufWriter<StdoutLock<'static>>) -> Result<S, E>,
+{
+    let mut out = stdout();
+    let result = body(&mut out);
+    // whatever was written before an error still goes out
+    let flushed = out.flush().map_err(E::from);
+    report(
+        result.and_then(|status| flushed.map(|_| status)),
+        error_status,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, fmt, io};
+
+    use super::{is_broken_pipe, report, EXIT_BROKEN_PIPE};
+
+    #[derive(Debug)]
+    struct Wrapper(io::Error);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn broken_pipe() -> io::Error {
+        io::Error::new(io::ErrorKind::BrokenPipe, "Broken pipe")
+    }
+
+    #[test]
+    fn test_is_broken_pipe() {
+        assert!(is_broken_pipe(&broken_pipe()));
+        assert!(is_broken_pipe(&Wrapper(broken_pipe())));
+        assert!(!is_broken_pipe(&Wrapper(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No such file"
+        ))));
+    }
+
+    #[test]
+    fn test_report() {
+        assert_eq!(report::<(), Wrapper>(Ok(()), 2), 0);
+        assert_eq!(report::<i32, Wrapper>(Ok(1), 2), 1);
+        assert_eq!(report::<(), _>(Err(Wrapper(io::Error::other("x"))), 2), 2);
+        assert_eq!(
+            report::<(), _>(Err(Wrapper(broken_pipe())), 2),
+            EXIT_BROKEN_PIPE
+        );
+    }
+}
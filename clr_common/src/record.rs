@@ -0,0 +1,124 @@
+//! Records ending in a newline, or in NUL with `-z/--zero-terminated`, so
+//! that paths containing newlines survive a pipeline.
+
+use std::io::{self, BufRead};
+
+use clap::{Arg, ArgMatches};
+
+use crate::config::Opt;
+
+/// The `-z/--zero-terminated` flag
+pub const OPT: Opt = Opt::flag("zero_terminated")
+    .short('z')
+    .long("zero-terminated");
+
+/// The `OPT` argument
+pub fn arg() -> Arg<'static, 'static> {
+    OPT.arg().help("Line delimiter is NUL, not newline")
+}
+
+/// The record terminator chosen with `arg()`
+pub fn terminator(matches: &ArgMatches) -> u8 {
+    if matches.is_present("zero_terminated") {
+        b'\0'
+    } else {
+        b'\n'
+    }
+}
+
+/// Like `BufRead::read_line`, but the record ends at `terminator`, and
+/// bytes that are not UTF-8 read as U+FFFD rather than failing the operand.
+pub fn read_record<R: BufRead + ?Sized>(
+    reader: &mut R,
+    terminator: u8,
+    buf: &mut String,
+) -> io::Result<usize> {
+    let mut bytes = vec![];
+    let bytes_read = reader.read_until(terminator, &mut bytes)?;
+    buf.push_str(&String::from_utf8_lossy(&bytes));
+    Ok(bytes_read)
+}
+
+/// Iterator over the records of a reader, see `records()`
+pub struct Records<R> {
+    reader: R,
+    terminator: u8,
+}
+
+/// Like `BufRead::lines`, but records end at `terminator`. With '\n' this is
+/// exactly `lines()`, including dropping a '\r' before the newline.
+pub fn records<R: BufRead>(reader: R, terminator: u8) -> Records<R> {
+    Records { reader, terminator }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = String::new();
+        match read_record(&mut self.reader, self.terminator, &mut buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                if buf.ends_with(self.terminator as char) {
+                    buf.pop();
+                    if self.terminator == b'\n' && buf.ends_with('\r') {
+                        buf.pop();
+                    }
+                }
+                Some(Ok(buf))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Cursor};
+
+    use super::{read_record, records};
+
+    fn collect(text: &str, terminator: u8) -> Vec<String> {
+        records(Cursor::new(text), terminator)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_records_newline() {
+        let text = "a\nb\r\n\nno newline";
+        assert_eq!(collect(text, b'\n'), ["a", "b", "", "no newline"]);
+
+        let lines: Vec<_> = Cursor::new(text).lines().map(Result::unwrap).collect();
+        assert_eq!(collect(text, b'\n'), lines);
+    }
+
+    #[test]
+    fn test_records_nul() {
+        assert_eq!(
+            collect("a\nb\0c\r\n\0\0d", b'\0'),
+            ["a\nb", "c\r\n", "", "d"]
+        );
+        assert!(collect("", b'\0').is_empty());
+    }
+
+    #[test]
+    fn test_records_invalid_utf8() {
+        let mut recs = records(Cursor::new(b"a\xffb\0c".to_vec()), b'\0');
+        assert_eq!(recs.next().unwrap().unwrap(), "a\u{fffd}b");
+        assert_eq!(recs.next().unwrap().unwrap(), "c");
+    }
+
+    #[test]
+    fn test_read_record() {
+        let mut reader = Cursor::new("one\0two");
+        let mut buf = String::new();
+        assert_eq!(read_record(&mut reader, b'\0', &mut buf).unwrap(), 4);
+        assert_eq!(buf, "one\0");
+        buf.clear();
+        assert_eq!(read_record(&mut reader, b'\0', &mut buf).unwrap(), 3);
+        assert_eq!(buf, "two");
+        buf.clear();
+        assert_eq!(read_record(&mut reader, b'\0', &mut buf).unwrap(), 0);
+    }
+}